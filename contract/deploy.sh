@@ -1,7 +1,10 @@
 #!/bin/sh
 
+# migrate converts the state of the first release and keeps the already converted one as it is
+ACCOUNT_ID=${1:-succinct-slave.testnet}
+
 ./build.sh
 
-echo ">> Deploying contract"
+echo ">> Deploying contract to $ACCOUNT_ID"
 
-near contract deploy succinct-slave.testnet use-file ./target/wasm32-unknown-unknown/release/mystery_box.wasm without-init-call network-config testnet sign-with-keychain send
\ No newline at end of file
+near contract deploy "$ACCOUNT_ID" use-file ./target/wasm32-unknown-unknown/release/mystery_box.wasm with-init-call migrate json-args '{}' prepaid-gas '300.0 Tgas' attached-deposit '0 NEAR' network-config testnet sign-with-keychain send
@@ -1,16 +1,23 @@
-use near_sdk::{env, log, near_bindgen, require, serde_json, AccountId, Gas, Promise, PromiseOrValue, PromiseResult};
+use std::collections::HashMap;
+
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, log, near_bindgen, require, serde_json, AccountId, Balance, Gas, Promise, PromiseOrValue,
+    PromiseResult,
+};
 
 use crate::{Contract, ContractExt};
 
-use super::{internal, json::JsonReward, types::{BoxId, BoxRarity, PoolId, QuestId, Reward, BoxStatus}};
+use super::{
+    internal,
+    json::JsonReward,
+    quest::Quest,
+    types::questbox_data::QuestBoxData,
+    types::{BoxId, BoxRarity, BoxStatus, PendingReward, PoolId, QuestId, Reward, TokenId},
+};
 
-pub(crate) fn create_withdraw_box_reward_promise_with_verification(
-    account_id: &AccountId,
-    quest_id: QuestId,
-    box_id: &BoxId,
-    pool_id: &PoolId,
-) -> Promise {
-    let get_iah_verification_promise = Promise::new(internal::get_registry_iah_contract()).function_call(
+fn create_iah_verification_promise(account_id: &AccountId) -> Promise {
+    Promise::new(internal::get_registry_iah_contract()).function_call(
         "sbt_tokens_by_owner".to_string(),
         serde_json::json!({
             "issuer":internal::get_issuer_iah_contract(),
@@ -20,7 +27,17 @@ pub(crate) fn create_withdraw_box_reward_promise_with_verification(
         .into_bytes(),
         0,
         Gas::ONE_TERA * 5,
-    );
+    )
+}
+
+pub(crate) fn create_withdraw_box_reward_promise_with_verification(
+    account_id: &AccountId,
+    quest_id: QuestId,
+    box_id: &BoxId,
+    pool_id: &PoolId,
+    escrowed: bool,
+) -> Promise {
+    let get_iah_verification_promise = create_iah_verification_promise(account_id);
 
     let on_iah_verification_callback_promise = Contract::ext(env::current_account_id())
         .with_static_gas(Gas::ONE_TERA * 10)
@@ -29,11 +46,115 @@ pub(crate) fn create_withdraw_box_reward_promise_with_verification(
             account_id.to_owned(),
             box_id.to_owned(),
             pool_id.to_owned(),
+            escrowed,
         );
 
     get_iah_verification_promise.then(on_iah_verification_callback_promise)
 }
 
+// the gas is reserved upfront for the worst case, when every reward is transferred separately
+pub(crate) fn create_claim_many_promise_with_verification(
+    account_id: &AccountId,
+    quest_id: QuestId,
+    claims: &Vec<(BoxId, PoolId)>,
+    transfer_gas: Gas,
+    escrowed: bool,
+) -> Promise {
+    let on_iah_verification_callback_promise = Contract::ext(env::current_account_id())
+        .with_static_gas(
+            Gas::ONE_TERA * 10 + transfer_gas + claim_many_transfer_callback_gas(claims.len()),
+        )
+        .check_iah_verification_and_claim_many_callback(
+            quest_id,
+            account_id.to_owned(),
+            claims.to_owned(),
+            escrowed,
+        );
+
+    create_iah_verification_promise(account_id).then(on_iah_verification_callback_promise)
+}
+
+fn claim_many_transfer_callback_gas(boxes_count: usize) -> Gas {
+    Gas::ONE_TERA * (5 + 2 * boxes_count as u64)
+}
+
+// the same assets are merged into a single transfer, each group keeps (transfer index, part index) of its rewards
+fn group_reward_transfers(
+    transfers: &[(BoxId, PoolId, Reward)],
+) -> Vec<(Reward, Vec<(usize, usize)>)> {
+    let mut groups: Vec<(Reward, Vec<(usize, usize)>)> = Vec::new();
+
+    transfers
+        .iter()
+        .enumerate()
+        .for_each(|(transfer_index, (_, _, reward))| {
+            reward
+                .to_parts()
+                .into_iter()
+                .enumerate()
+                .for_each(|(part_index, part)| {
+                    match groups
+                        .iter_mut()
+                        .position(|(grouped_reward, _)| grouped_reward.merge(&part))
+                    {
+                        Some(group_index) => {
+                            groups[group_index].1.push((transfer_index, part_index))
+                        }
+                        None => groups.push((part, vec![(transfer_index, part_index)])),
+                    }
+                });
+        });
+
+    groups
+}
+
+fn create_claim_many_transfer_promise(
+    receiver_id: &AccountId,
+    quest_id: QuestId,
+    claims: &Vec<(BoxId, PoolId)>,
+    transfers: &Vec<(BoxId, PoolId, Reward)>,
+) -> Promise {
+    let transfer_promise = group_reward_transfers(transfers)
+        .iter()
+        .map(|(reward, _)| create_transfer_reward_promise(receiver_id, reward))
+        .reduce(|joined_promise, promise| joined_promise.and(promise))
+        .expect("ERR_NO_REWARDS_TO_TRANSFER");
+
+    let on_transfer_promise = Contract::ext(env::current_account_id())
+        .with_static_gas(claim_many_transfer_callback_gas(claims.len()))
+        .claim_many_transfer_callback(
+            quest_id,
+            receiver_id.to_owned(),
+            claims.to_owned(),
+            transfers.to_owned(),
+        );
+
+    transfer_promise.then(on_transfer_promise)
+}
+
+pub(crate) fn transfer_reward_gas(reward: &Reward) -> Gas {
+    match reward {
+        Reward::Near { .. } => Gas(0),
+        Reward::NonFungibleTokenMint { .. } => Gas::ONE_TERA * 10,
+        Reward::Bundle { rewards } => rewards
+            .iter()
+            .map(|bundled_reward| transfer_reward_gas(&bundled_reward.reward))
+            .fold(Gas(0), |total, gas| total + gas),
+        _ => Gas::ONE_TERA * 5,
+    }
+}
+
+fn claimed_box_result(quest: &Quest, box_id: &BoxId) -> (BoxId, BoxRarity, JsonReward) {
+    let box_data = quest.boxes.get(box_id).expect("ERR_BOX_NOT_FOUND");
+
+    let reward = match box_data.box_status {
+        BoxStatus::NonClaimed | BoxStatus::Expired => unreachable!(),
+        BoxStatus::Claimed { reward } => reward,
+    };
+
+    (box_data.box_id, box_data.box_rarity, reward.into())
+}
+
 pub(crate) fn create_withdraw_box_reward_promise(
     receiver_id: &AccountId,
     quest_id: QuestId,
@@ -81,6 +202,262 @@ fn create_transfer_reward_promise(receiver_id: &AccountId, reward: &Reward) -> P
             1,
             Gas::ONE_TERA * 5,
         ),
+        Reward::FungibleToken {
+            contract_id,
+            amount,
+        } => Promise::new(contract_id.to_owned()).function_call(
+            "ft_transfer".to_string(),
+            serde_json::json!({
+                "receiver_id": receiver_id.clone(),
+                "amount": U128(amount.to_owned())
+            })
+            .to_string()
+            .into_bytes(),
+            1,
+            Gas::ONE_TERA * 5,
+        ),
+        Reward::NonFungibleTokenMint {
+            contract_id,
+            token_id,
+            metadata,
+            deposit,
+        } => Promise::new(contract_id.to_owned()).function_call(
+            "nft_mint".to_string(),
+            serde_json::json!({
+                "token_id": token_id.clone(),
+                "receiver_id": receiver_id.clone(),
+                "token_metadata": metadata.clone()
+            })
+            .to_string()
+            .into_bytes(),
+            deposit.to_owned(),
+            Gas::ONE_TERA * 10,
+        ),
+        Reward::MultiToken {
+            contract_id,
+            token_id,
+            amount,
+        } => Promise::new(contract_id.to_owned()).function_call(
+            "mt_transfer".to_string(),
+            serde_json::json!({
+                "receiver_id": receiver_id.clone(),
+                "token_id": token_id.clone(),
+                "amount": U128(amount.to_owned())
+            })
+            .to_string()
+            .into_bytes(),
+            1,
+            Gas::ONE_TERA * 5,
+        ),
+        // parts are joined, so the callback receives a separate result for each of them
+        Reward::Bundle { rewards } => rewards
+            .iter()
+            .map(|bundled_reward| {
+                create_transfer_reward_promise(receiver_id, &bundled_reward.reward)
+            })
+            .reduce(|joined_promise, promise| joined_promise.and(promise))
+            .expect("ERR_EMPTY_BUNDLE"),
+    }
+}
+
+pub(crate) fn create_withdraw_pending_reward_promise(
+    receiver_id: &AccountId,
+    pending_reward: &PendingReward,
+) -> Promise {
+    let on_withdraw_promise = Contract::ext(env::current_account_id())
+        .with_static_gas(Gas::ONE_TERA * 5)
+        .withdraw_pending_reward_callback(receiver_id.to_owned(), pending_reward.to_owned());
+
+    create_transfer_reward_promise(receiver_id, &pending_reward.reward).then(on_withdraw_promise)
+}
+
+pub(crate) fn create_withdraw_vested_promise(
+    receiver_id: &AccountId,
+    quest_id: QuestId,
+    withdrawn: &[(BoxId, Balance)],
+) -> Promise {
+    let amount: Balance = withdrawn.iter().map(|(_, amount)| amount).sum();
+
+    let on_withdraw_promise = Contract::ext(env::current_account_id())
+        .with_static_gas(Gas::ONE_TERA * 5)
+        .withdraw_vested_callback(
+            quest_id,
+            receiver_id.to_owned(),
+            withdrawn
+                .iter()
+                .map(|(box_id, amount)| (box_id.to_owned(), U128(amount.to_owned())))
+                .collect(),
+        );
+
+    Promise::new(receiver_id.to_owned())
+        .transfer(amount)
+        .then(on_withdraw_promise)
+}
+
+pub(crate) fn create_withdraw_fees_promise(receiver_id: &AccountId, amount: Balance) -> Promise {
+    let on_withdraw_promise = Contract::ext(env::current_account_id())
+        .with_static_gas(Gas::ONE_TERA * 5)
+        .withdraw_fees_callback(receiver_id.to_owned(), U128(amount));
+
+    Promise::new(receiver_id.to_owned())
+        .transfer(amount)
+        .then(on_withdraw_promise)
+}
+
+pub(crate) fn create_withdraw_revenue_promise(
+    receiver_id: &AccountId,
+    quest_id: QuestId,
+    amount: Balance,
+) -> Promise {
+    let on_withdraw_promise = Contract::ext(env::current_account_id())
+        .with_static_gas(Gas::ONE_TERA * 5)
+        .withdraw_revenue_callback(quest_id, receiver_id.to_owned(), U128(amount));
+
+    Promise::new(receiver_id.to_owned())
+        .transfer(amount)
+        .then(on_withdraw_promise)
+}
+
+pub(crate) fn create_withdraw_nft_tokens_promise(
+    receiver_id: &AccountId,
+    quest_id: QuestId,
+    pool_id: &PoolId,
+    contract_id: &AccountId,
+    token_ids: &[TokenId],
+) -> Promise {
+    token_ids
+        .iter()
+        .map(|token_id| {
+            let reward = Reward::NonFungibleToken {
+                contract_id: contract_id.to_owned(),
+                token_id: token_id.to_owned(),
+            };
+
+            let on_withdraw_promise = Contract::ext(env::current_account_id())
+                .with_static_gas(Gas::ONE_TERA * 5)
+                .withdraw_nft_token_callback(
+                    quest_id,
+                    receiver_id.to_owned(),
+                    pool_id.to_owned(),
+                    token_id.to_owned(),
+                );
+
+            create_transfer_reward_promise(receiver_id, &reward).then(on_withdraw_promise)
+        })
+        .reduce(|joined_promise, promise| joined_promise.and(promise))
+        .expect("ERR_NO_TOKENS_TO_WITHDRAW")
+}
+
+pub(crate) fn create_nft_transfer_call_promise(
+    previous_owner_id: &AccountId,
+    receiver_id: &AccountId,
+    token_id: &TokenId,
+    msg: String,
+) -> Promise {
+    let on_transfer_promise = Promise::new(receiver_id.to_owned()).function_call(
+        "nft_on_transfer".to_string(),
+        serde_json::json!({
+            "sender_id": previous_owner_id.clone(),
+            "previous_owner_id": previous_owner_id.clone(),
+            "token_id": token_id.clone(),
+            "msg": msg
+        })
+        .to_string()
+        .into_bytes(),
+        0,
+        Gas::ONE_TERA * 25,
+    );
+
+    let resolve_transfer_promise = Contract::ext(env::current_account_id())
+        .with_static_gas(Gas::ONE_TERA * 10)
+        .nft_resolve_transfer(
+            previous_owner_id.to_owned(),
+            receiver_id.to_owned(),
+            token_id.to_owned(),
+        );
+
+    on_transfer_promise.then(resolve_transfer_promise)
+}
+
+impl Contract {
+    /// Returns the reward if it has to be transferred right away, otherwise it's handled within the contract
+    fn internal_reward_to_transfer(
+        &self,
+        quest: &Quest,
+        receiver_id: &AccountId,
+        box_id: BoxId,
+        escrowed: bool,
+    ) -> Option<Reward> {
+        let box_data = quest.boxes.get(&box_id).expect("ERR_BOX_NOT_FOUND");
+
+        let reward = match box_data.box_status {
+            BoxStatus::NonClaimed | BoxStatus::Expired => unreachable!(),
+            BoxStatus::Claimed { reward } => reward,
+        };
+
+        match reward {
+            Option::None => None,
+            // vested rewards are released with withdraw_vested
+            Option::Some(_) if quest.internal_is_vested(receiver_id, box_id) => None,
+            // the reward has been locked in with the claim, so a failed transfer can't be turned into another roll
+            Option::Some(_) if escrowed => None,
+            Option::Some(reward) => Some(reward),
+        }
+    }
+
+    /// Undoes the claim for the failed parts of the reward and returns what has been transferred
+    fn internal_resolve_reward_transfer(
+        &mut self,
+        quest_id: QuestId,
+        account_id: &AccountId,
+        box_id: BoxId,
+        pool_id: PoolId,
+        reward: Reward,
+        failed_parts: Vec<usize>,
+    ) -> Option<(BoxId, BoxRarity, JsonReward)> {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let box_data = quest.boxes.get(&box_id).expect("ERR_BOX_NOT_FOUND");
+
+        if failed_parts.is_empty() {
+            log!(
+                "Successfully transferred box {} reward to {}",
+                box_id,
+                account_id
+            );
+
+            return Some((box_data.box_id, box_data.box_rarity, reward.into()));
+        }
+
+        if failed_parts.len() == reward.parts_count() {
+            log!(
+                "Something failed while transferring box {} reward to {}",
+                box_id,
+                account_id
+            );
+
+            quest.internal_undo_claim(box_id, pool_id);
+
+            return None;
+        }
+
+        log!(
+            "Something failed while transferring {} of {} parts of box {} reward to {}",
+            failed_parts.len(),
+            reward.parts_count(),
+            box_id,
+            account_id
+        );
+
+        let transferred_reward = quest.internal_undo_claim_partially(box_id, &failed_parts);
+
+        Some((
+            box_data.box_id,
+            box_data.box_rarity,
+            transferred_reward.into(),
+        ))
     }
 }
 
@@ -93,6 +470,7 @@ impl Contract {
         receiver_id: AccountId,
         box_id: BoxId,
         pool_id: PoolId,
+        escrowed: bool,
     ) -> PromiseOrValue<Option<(BoxId, BoxRarity, JsonReward)>> {
         // https://docs.rs/near-sdk/latest/near_sdk/env/fn.promise_results_count.html
         assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
@@ -147,17 +525,8 @@ impl Contract {
         //     return PromiseOrValue::Value(None);
         // };
 
-        let box_data = quest.boxes.get(&box_id).expect("ERR_BOX_NOT_FOUND");
-
-        let reward = match box_data.box_status {
-            BoxStatus::NonClaimed => unreachable!(),
-            BoxStatus::Claimed { reward } => reward,
-        };
-
-        match reward {
-            Option::None => {
-                PromiseOrValue::Value(Some((box_data.box_id, box_data.box_rarity, reward.into())))
-            }
+        match self.internal_reward_to_transfer(&quest, &receiver_id, box_id, escrowed) {
+            Option::None => PromiseOrValue::Value(Some(claimed_box_result(&quest, &box_id))),
             Option::Some(reward) => PromiseOrValue::Promise(create_withdraw_box_reward_promise(
                 &receiver_id,
                 quest_id,
@@ -168,6 +537,109 @@ impl Contract {
         }
     }
 
+    /// Single verification for all the boxes, their rewards are transferred with merged promises
+    #[private]
+    pub fn check_iah_verification_and_claim_many_callback(
+        &mut self,
+        quest_id: QuestId,
+        receiver_id: AccountId,
+        claims: Vec<(BoxId, PoolId)>,
+        escrowed: bool,
+    ) -> PromiseOrValue<Vec<Option<(BoxId, BoxRarity, JsonReward)>>> {
+        assert_eq!(env::promise_results_count(), 1, "ERR_TOO_MANY_RESULTS");
+
+        //TODO: Fix verification, the same way as for a single claim
+
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let transfers = claims
+            .iter()
+            .filter_map(|(box_id, pool_id)| {
+                self.internal_reward_to_transfer(&quest, &receiver_id, *box_id, escrowed)
+                    .map(|reward| (*box_id, *pool_id, reward))
+            })
+            .collect::<Vec<(BoxId, PoolId, Reward)>>();
+
+        if transfers.is_empty() {
+            return PromiseOrValue::Value(
+                claims
+                    .iter()
+                    .map(|(box_id, _)| Some(claimed_box_result(&quest, box_id)))
+                    .collect(),
+            );
+        }
+
+        PromiseOrValue::Promise(create_claim_many_transfer_promise(
+            &receiver_id,
+            quest_id,
+            &claims,
+            &transfers,
+        ))
+    }
+
+    #[private]
+    pub fn claim_many_transfer_callback(
+        &mut self,
+        quest_id: QuestId,
+        receiver_id: AccountId,
+        claims: Vec<(BoxId, PoolId)>,
+        transfers: Vec<(BoxId, PoolId, Reward)>,
+    ) -> Vec<Option<(BoxId, BoxRarity, JsonReward)>> {
+        let groups = group_reward_transfers(&transfers);
+
+        require!(
+            env::promise_results_count() == groups.len() as u64,
+            "ERR_TOO_MANY_RESULTS"
+        );
+
+        let mut failed_parts: Vec<Vec<usize>> = vec![Vec::new(); transfers.len()];
+
+        groups
+            .iter()
+            .enumerate()
+            .for_each(|(group_index, (_, parts))| {
+                if let PromiseResult::Successful(_) = env::promise_result(group_index as u64) {
+                    return;
+                }
+
+                parts.iter().for_each(|(transfer_index, part_index)| {
+                    failed_parts[*transfer_index].push(*part_index);
+                });
+            });
+
+        let mut transferred = transfers
+            .into_iter()
+            .zip(failed_parts)
+            .map(|((box_id, pool_id, reward), failed_parts)| {
+                let result = self.internal_resolve_reward_transfer(
+                    quest_id,
+                    &receiver_id,
+                    box_id,
+                    pool_id,
+                    reward,
+                    failed_parts,
+                );
+
+                (box_id, result)
+            })
+            .collect::<HashMap<BoxId, Option<(BoxId, BoxRarity, JsonReward)>>>();
+
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        claims
+            .iter()
+            .map(|(box_id, _)| {
+                transferred
+                    .remove(box_id)
+                    .unwrap_or_else(|| Some(claimed_box_result(&quest, box_id)))
+            })
+            .collect()
+    }
+
     #[private]
     pub fn transfer_reward_callback(
         &mut self,
@@ -178,35 +650,241 @@ impl Contract {
         reward: Reward,
     ) -> Option<(BoxId, BoxRarity, JsonReward)> {
         // https://docs.rs/near-sdk/latest/near_sdk/env/fn.promise_results_count.html
-        require!(env::promise_results_count() == 1, "ERR_TOO_MANY_RESULTS");
+        require!(
+            env::promise_results_count() == reward.parts_count() as u64,
+            "ERR_TOO_MANY_RESULTS"
+        );
 
-        let transfer_result = env::promise_result(0);
+        let failed_parts = (0..reward.parts_count())
+            .filter(|index| {
+                !matches!(
+                    env::promise_result(*index as u64),
+                    PromiseResult::Successful(_)
+                )
+            })
+            .collect::<Vec<usize>>();
+
+        self.internal_resolve_reward_transfer(
+            quest_id,
+            &account_id,
+            box_id,
+            pool_id,
+            reward,
+            failed_parts,
+        )
+    }
+    #[private]
+    pub fn withdraw_pending_reward_callback(
+        &mut self,
+        receiver_id: AccountId,
+        pending_reward: PendingReward,
+    ) -> bool {
+        let parts_count = pending_reward.reward.parts_count();
+
+        require!(
+            env::promise_results_count() == parts_count as u64,
+            "ERR_TOO_MANY_RESULTS"
+        );
 
-        let mut quest = self.quests.get(&quest_id).expect(&format!("Quest with id {} wasn't found", quest_id.clone()));
+        let failed_parts = (0..parts_count)
+            .filter(|index| {
+                !matches!(
+                    env::promise_result(*index as u64),
+                    PromiseResult::Successful(_)
+                )
+            })
+            .collect::<Vec<usize>>();
+
+        if failed_parts.is_empty() {
+            log!(
+                "Successfully transferred box {} reward to {}",
+                pending_reward.box_id,
+                receiver_id
+            );
 
-        match transfer_result {
+            return true;
+        }
+
+        log!(
+            "Something failed while transferring {} of {} parts of box {} reward to {}",
+            failed_parts.len(),
+            parts_count,
+            pending_reward.box_id,
+            receiver_id
+        );
+
+        // failed parts stay in the escrow to be retried with the next withdrawal
+        self.internal_add_pending_reward(
+            &receiver_id,
+            PendingReward {
+                reward: pending_reward.reward.clone().parts(&failed_parts),
+                ..pending_reward
+            },
+        );
+
+        false
+    }
+
+    #[private]
+    pub fn withdraw_vested_callback(
+        &mut self,
+        quest_id: QuestId,
+        receiver_id: AccountId,
+        withdrawn: Vec<(BoxId, U128)>,
+    ) -> bool {
+        require!(env::promise_results_count() == 1, "ERR_TOO_MANY_RESULTS");
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        match env::promise_result(0) {
             PromiseResult::Successful(_) => {
+                quest.internal_remove_withdrawn_vesting_positions(&receiver_id);
+
+                true
+            }
+            _ => {
                 log!(
-                    "Successfully transferred box {} reward to {}",
-                    box_id,
-                    account_id
+                    "Something failed while withdrawing vested rewards to {}",
+                    receiver_id
                 );
 
-                let box_data = quest.boxes.get(&box_id).unwrap();
+                quest.internal_revert_vested_withdrawal(
+                    &receiver_id,
+                    &withdrawn
+                        .into_iter()
+                        .map(|(box_id, amount)| (box_id, amount.0))
+                        .collect::<Vec<_>>(),
+                );
 
-                Some((box_data.box_id, box_data.box_rarity, reward.into()))
+                false
             }
+        }
+    }
+
+    #[private]
+    pub fn withdraw_fees_callback(&mut self, receiver_id: AccountId, amount: U128) -> bool {
+        require!(env::promise_results_count() == 1, "ERR_TOO_MANY_RESULTS");
+
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => true,
+            _ => {
+                log!("Something failed while withdrawing fees to {}", receiver_id);
+
+                self.pending_fees += amount.0;
+
+                false
+            }
+        }
+    }
+
+    #[private]
+    pub fn withdraw_revenue_callback(
+        &mut self,
+        quest_id: QuestId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        require!(env::promise_results_count() == 1, "ERR_TOO_MANY_RESULTS");
+
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => true,
             _ => {
                 log!(
-                    "Something failed while transferring box {} reward to {}",
-                    box_id,
-                    account_id
+                    "Something failed while withdrawing revenue to {}",
+                    receiver_id
+                );
+
+                let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+                    env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+                });
+
+                quest.revenue += amount.0;
+
+                self.quests.insert(&quest_id, &quest);
+
+                false
+            }
+        }
+    }
+
+    #[private]
+    pub fn withdraw_nft_token_callback(
+        &mut self,
+        quest_id: QuestId,
+        receiver_id: AccountId,
+        pool_id: PoolId,
+        token_id: TokenId,
+    ) -> bool {
+        require!(env::promise_results_count() == 1, "ERR_TOO_MANY_RESULTS");
+
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                log!(
+                    "Successfully withdrew token {} to {}",
+                    token_id,
+                    receiver_id
+                );
+
+                true
+            }
+            _ => {
+                log!(
+                    "Something failed while withdrawing token {} to {}",
+                    token_id,
+                    receiver_id
                 );
 
-                quest.internal_undo_claim(box_id, pool_id);
+                let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+                    env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+                });
 
-                None
+                // the token is still owned by the contract, so it goes back to the pool
+                quest.internal_return_nft_token(pool_id, token_id);
+
+                false
+            }
+        }
+    }
+
+    /// Returns the box to the previous owner if the receiver asked for it or failed, true if the box was kept
+    #[private]
+    pub fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+    ) -> bool {
+        require!(env::promise_results_count() == 1, "ERR_TOO_MANY_RESULTS");
+
+        let must_return = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                serde_json::from_slice::<bool>(&value).unwrap_or(true)
             }
+            _ => true,
+        };
+
+        if !must_return {
+            return true;
         }
+
+        let questbox_data = QuestBoxData::from_token_id(&token_id).expect("ERR_INVALID_TOKEN_ID");
+
+        let is_owned_by_receiver = self
+            .quests
+            .get(&questbox_data.quest_id)
+            .and_then(|quest| quest.boxes.get(&questbox_data.box_id))
+            .map(|questbox| questbox.owner_id == receiver_id)
+            .unwrap_or(false);
+
+        // the receiver could have already transferred the box further
+        if !is_owned_by_receiver {
+            return true;
+        }
+
+        self.internal_transfer_box(&receiver_id, &previous_owner_id, &questbox_data, None);
+
+        false
     }
 }
@@ -1,20 +1,80 @@
 use crate::contract::quest::Quest;
-use near_sdk::json_types::U128;
-use near_sdk::AccountId;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, AccountId};
 
-use super::json::{JsonPoolRewards, Pagination};
+use super::json::{
+    JsonBox, JsonBoxCapacity, JsonPool, JsonPoolOdds, JsonRarityTier, JsonVestingPosition,
+    Pagination,
+};
+use super::questbox::QuestBox;
 use super::types::BoxRarity;
 
 impl Quest {
     pub fn total_supply(&self) -> U128 {
-        U128(self.next_box_id - 1)
+        U128(self.next_box_id - self.deleted_boxes)
+    }
+
+    pub fn box_to_json(&self, questbox: QuestBox) -> JsonBox {
+        let media = self.rarity_media(&questbox.box_rarity);
+
+        JsonBox::new(
+            questbox.quest_id,
+            questbox.box_id,
+            questbox.box_rarity,
+            questbox.box_status.into(),
+            media,
+            questbox.expires_at.map(U64),
+        )
+    }
+
+    pub fn rarity_tiers(&self) -> Vec<JsonRarityTier> {
+        self.tiers()
+            .into_iter()
+            .map(|tier| JsonRarityTier {
+                media: Some(self.rarity_media(&tier.rarity).media),
+                probability: self.probability_by_rarity.get(&tier.rarity),
+                rarity: tier.rarity,
+            })
+            .collect()
+    }
+
+    pub fn box_capacity(&self, rarity: BoxRarity) -> JsonBoxCapacity {
+        let minted = self.boxes_by_rarity.get(&rarity).unwrap_or(0);
+        let max_boxes = self.max_boxes_by_rarity.get(&rarity);
+
+        let remaining_by_rarity = max_boxes.map(|max_boxes| max_boxes.saturating_sub(minted));
+        let remaining_total = self
+            .max_boxes
+            .map(|max_boxes| max_boxes.saturating_sub(self.total_supply().0 as u64));
+
+        let remaining = match (remaining_by_rarity, remaining_total) {
+            (Some(by_rarity), Some(total)) => Some(by_rarity.min(total)),
+            (by_rarity, total) => by_rarity.or(total),
+        };
+
+        let funded = self
+            .pool_ids_by_rarity
+            .get(&rarity)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|pool_id| self.pools.get(pool_id))
+            .map(|pool| self.internal_pool_availability(&pool))
+            .sum();
+
+        JsonBoxCapacity {
+            rarity,
+            minted: U64(minted),
+            max_boxes: max_boxes.map(U64),
+            remaining: remaining.map(U64),
+            funded: U64(funded),
+        }
     }
 
     pub fn available_rewards(
         &self,
         rarity: BoxRarity,
         pagination: Option<Pagination>,
-    ) -> Vec<JsonPoolRewards> {
+    ) -> Vec<JsonPool> {
         let pagination = pagination.unwrap_or_default();
 
         pagination.assert_valid();
@@ -25,10 +85,10 @@ impl Quest {
             .iter()
             .map(|pool_id| self.pools.get(pool_id))
             .flatten()
-            .filter(|pool| !pool.is_empty())
+            .filter(|pool| self.internal_pool_availability(pool) > 0)
             .take(pagination.take())
             .skip(pagination.skip())
-            .map(|pool| pool.into())
+            .map(|pool| self.internal_pool_to_json(pool))
             .collect()
     }
 
@@ -36,7 +96,7 @@ impl Quest {
         &self,
         rarity: BoxRarity,
         pagination: Option<Pagination>,
-    ) -> Vec<JsonPoolRewards> {
+    ) -> Vec<JsonPool> {
         let pagination = pagination.unwrap_or_default();
 
         pagination.assert_valid();
@@ -49,7 +109,46 @@ impl Quest {
             .flatten()
             .take(pagination.take())
             .skip(pagination.skip())
-            .map(|pool| pool.into())
+            .map(|pool| self.internal_pool_to_json(pool))
+            .collect()
+    }
+
+    pub fn pool_odds(&self, rarity: BoxRarity) -> Vec<JsonPoolOdds> {
+        let mut weighted_pools = self
+            .pool_ids_by_rarity
+            .get(&rarity)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|pool_id| self.pools.get(pool_id))
+            .map(|pool| (pool.id, self.internal_pool_weight(&pool)))
+            .filter(|(_, weight)| *weight > 0)
+            .collect::<Vec<_>>();
+
+        weighted_pools.sort_by_key(|(pool_id, _)| *pool_id);
+
+        let total_weight: u128 = weighted_pools
+            .iter()
+            .map(|(_, weight)| *weight as u128)
+            .sum();
+
+        weighted_pools
+            .into_iter()
+            .map(|(pool_id, weight)| JsonPoolOdds {
+                pool_id,
+                weight: U64(weight),
+                total_weight: U128(total_weight),
+            })
+            .collect()
+    }
+
+    pub fn vesting_positions(&self, account_id: &AccountId) -> Vec<JsonVestingPosition> {
+        let now = env::block_timestamp();
+
+        self.vesting_positions
+            .get(account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|position| JsonVestingPosition::new(position, now))
             .collect()
     }
 
@@ -32,4 +32,21 @@ pub enum StorageKey {
     QuestBoxesData { account_hash: CryptoHash },
     QuestIdsPerOwner { account_hash: CryptoHash },
     TrustedNftContracts,
+    TrustedFtContracts,
+    TrustedMtContracts,
+    Sponsors { quest_hash: CryptoHash },
+    FeesPerQuest,
+    PendingRewards,
+    VestingPositions { quest_hash: CryptoHash },
+    ExpiryByRarity { quest_hash: CryptoHash },
+    KeyedBoxes,
+    SalesByRarity { quest_hash: CryptoHash },
+    MaxBoxesByRarity { quest_hash: CryptoHash },
+    BoxesByRarity { quest_hash: CryptoHash },
+    MintedPerAccount { quest_hash: CryptoHash },
+    MediaByRarity { quest_hash: CryptoHash },
+    KeyedBoxKeys,
+    SoldBoxes { quest_hash: CryptoHash },
+    UsedVoucherNonces { quest_hash: CryptoHash },
+    QuestBoxes,
 }
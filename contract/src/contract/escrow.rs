@@ -0,0 +1,98 @@
+use near_sdk::{assert_one_yocto, env, near_bindgen, require, AccountId, Promise, StorageUsage};
+
+use crate::{Contract, ContractExt};
+
+use super::callbacks::create_withdraw_pending_reward_promise;
+use super::json::JsonPendingReward;
+use super::quest::Quest;
+use super::types::{BoxId, BoxStatus, PendingReward};
+
+const MAX_REWARD_WITHDRAWALS_PER_CALL: usize = 10;
+
+impl Contract {
+    pub(crate) fn internal_add_pending_reward(
+        &mut self,
+        account_id: &AccountId,
+        pending_reward: PendingReward,
+    ) -> StorageUsage {
+        let storage_used_before = env::storage_usage();
+
+        let mut pending_rewards = self.pending_rewards.get(account_id).unwrap_or_default();
+
+        pending_rewards.push(pending_reward);
+
+        self.pending_rewards.insert(account_id, &pending_rewards);
+
+        env::storage_usage().saturating_sub(storage_used_before)
+    }
+
+    /// Locks in the rewards of the claimed boxes, returns the storage taken by them
+    pub(crate) fn internal_escrow_rewards(
+        &mut self,
+        quest: &Quest,
+        account_id: &AccountId,
+        box_ids: &[BoxId],
+    ) -> StorageUsage {
+        box_ids
+            .iter()
+            .filter_map(|box_id| {
+                let box_data = quest.boxes.get(box_id).expect("ERR_BOX_NOT_FOUND");
+
+                match box_data.box_status {
+                    // vested rewards are released with withdraw_vested
+                    BoxStatus::Claimed {
+                        reward: Some(reward),
+                    } if !quest.internal_is_vested(account_id, *box_id) => Some(PendingReward {
+                        quest_id: quest.id,
+                        box_id: *box_id,
+                        reward,
+                    }),
+                    _ => None,
+                }
+            })
+            .map(|pending_reward| self.internal_add_pending_reward(account_id, pending_reward))
+            .sum()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Pays out the escrowed rewards, those which fail to transfer are put back to the escrow
+    #[payable]
+    pub fn withdraw_rewards(&mut self) -> Promise {
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+
+        let mut pending_rewards = self.pending_rewards.get(&account_id).unwrap_or_default();
+
+        require!(!pending_rewards.is_empty(), "ERR_NO_REWARDS_TO_WITHDRAW");
+
+        let withdrawn_rewards = pending_rewards
+            .drain(..pending_rewards.len().min(MAX_REWARD_WITHDRAWALS_PER_CALL))
+            .collect::<Vec<PendingReward>>();
+
+        if pending_rewards.is_empty() {
+            self.pending_rewards.remove(&account_id);
+        } else {
+            self.pending_rewards.insert(&account_id, &pending_rewards);
+        }
+
+        withdrawn_rewards
+            .iter()
+            .map(|pending_reward| {
+                create_withdraw_pending_reward_promise(&account_id, pending_reward)
+            })
+            .reduce(|joined_promise, promise| joined_promise.and(promise))
+            .unwrap()
+    }
+
+    pub fn pending_rewards(&self, account_id: AccountId) -> Vec<JsonPendingReward> {
+        self.pending_rewards
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|pending_reward| pending_reward.into())
+            .collect()
+    }
+}
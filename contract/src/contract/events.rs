@@ -0,0 +1,157 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::Serialize;
+use near_sdk::{env, serde_json, AccountId};
+
+use super::types::{PoolId, QuestId, TokenId};
+
+const EVENT_STANDARD: &str = "mystery_box";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+const NFT_EVENT_STANDARD: &str = "nep171";
+const NFT_EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+// https://nomicon.io/Standards/EventsFormat
+#[derive(Serialize, Debug)]
+#[serde(
+    crate = "near_sdk::serde",
+    tag = "event",
+    content = "data",
+    rename_all = "snake_case"
+)]
+pub enum Event {
+    NearPoolWithdrawal(Vec<NearPoolWithdrawalData>),
+    NearRangePoolWithdrawal(Vec<NearRangePoolWithdrawalData>),
+    NftPoolWithdrawal(Vec<NftPoolWithdrawalData>),
+    SponsoredPoolRefund(Vec<SponsoredPoolRefundData>),
+    FeeWithdrawal(Vec<FeeWithdrawalData>),
+    RevenueWithdrawal(Vec<RevenueWithdrawalData>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NearPoolWithdrawalData {
+    pub quest_id: QuestId,
+    pub pool_id: PoolId,
+    pub receiver_id: AccountId,
+    pub count: U64,
+    pub amount: U128,
+    pub storage_refund: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NearRangePoolWithdrawalData {
+    pub quest_id: QuestId,
+    pub pool_id: PoolId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    pub storage_refund: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftPoolWithdrawalData {
+    pub quest_id: QuestId,
+    pub pool_id: PoolId,
+    pub receiver_id: AccountId,
+    pub contract_id: AccountId,
+    pub token_ids: Vec<TokenId>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SponsoredPoolRefundData {
+    pub quest_id: QuestId,
+    pub pool_id: PoolId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    pub token_ids: Vec<TokenId>,
+    pub storage_refund: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeWithdrawalData {
+    pub receiver_id: AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RevenueWithdrawalData {
+    pub quest_id: QuestId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+}
+
+// https://nomicon.io/Standards/Tokens/NonFungibleToken/Event
+#[derive(Serialize, Debug)]
+#[serde(
+    crate = "near_sdk::serde",
+    tag = "event",
+    content = "data",
+    rename_all = "snake_case"
+)]
+pub enum NftEvent {
+    NftMint(Vec<NftMintData>),
+    NftTransfer(Vec<NftTransferData>),
+    NftBurn(Vec<NftBurnData>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftMintData {
+    pub owner_id: AccountId,
+    pub token_ids: Vec<TokenId>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftBurnData {
+    pub owner_id: AccountId,
+    pub token_ids: Vec<TokenId>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftTransferData {
+    pub old_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+    pub token_ids: Vec<TokenId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a, T: Serialize> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a T,
+}
+
+fn emit_event<T: Serialize>(standard: &'static str, version: &'static str, event: &T) {
+    let log = EventLog {
+        standard,
+        version,
+        event,
+    };
+
+    env::log_str(&format!(
+        "EVENT_JSON:{}",
+        serde_json::to_string(&log).unwrap()
+    ));
+}
+
+impl Event {
+    pub fn emit(&self) {
+        emit_event(EVENT_STANDARD, EVENT_STANDARD_VERSION, self);
+    }
+}
+
+impl NftEvent {
+    pub fn emit(&self) {
+        emit_event(NFT_EVENT_STANDARD, NFT_EVENT_STANDARD_VERSION, self);
+    }
+}
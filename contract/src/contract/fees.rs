@@ -0,0 +1,115 @@
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, require, AccountId, Balance, Promise};
+
+use crate::{Contract, ContractExt};
+
+use super::callbacks::create_withdraw_fees_promise;
+use super::events::{Event, FeeWithdrawalData};
+use super::json::{JsonFees, JsonQuestFees};
+use super::quest::Quest;
+use super::types::{BasisPoints, QuestId};
+
+const MAX_FEE_BPS: BasisPoints = 10_000;
+
+impl Contract {
+    fn assert_only_admin(&self) {
+        require!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "ERR_FORBIDDEN"
+        );
+    }
+
+    pub(crate) fn internal_fee_bps(&self, quest: &Quest) -> BasisPoints {
+        quest.fee_bps.unwrap_or(self.fee_bps)
+    }
+
+    /// Accrues the platform fee for the deposit and returns its amount, which is either attached on top of it or taken out of it
+    pub(crate) fn internal_take_fee(&mut self, quest: &Quest, deposit: Balance) -> Balance {
+        let fee = deposit * (self.internal_fee_bps(quest) as u128) / (MAX_FEE_BPS as u128);
+
+        if fee > 0 {
+            let collected = self.fees_per_quest.get(&quest.id).unwrap_or(0);
+
+            self.fees_per_quest.insert(&quest.id, &(collected + fee));
+            self.pending_fees += fee;
+        }
+
+        fee
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_fee(&mut self, fee_bps: BasisPoints) {
+        self.assert_only_admin();
+
+        require!(fee_bps <= MAX_FEE_BPS, "ERR_INVALID_FEE");
+
+        self.fee_bps = fee_bps;
+    }
+
+    pub fn set_quest_fee(&mut self, quest_id: QuestId, fee_bps: Option<BasisPoints>) {
+        self.assert_only_admin();
+
+        require!(
+            fee_bps.unwrap_or_default() <= MAX_FEE_BPS,
+            "ERR_INVALID_FEE"
+        );
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.fee_bps = fee_bps;
+
+        self.quests.insert(&quest_id, &quest);
+    }
+
+    pub fn set_fee_treasury(&mut self, treasury_id: AccountId) {
+        self.assert_only_admin();
+
+        self.fee_treasury_id = treasury_id;
+    }
+
+    pub fn withdraw_fees(&mut self) -> Promise {
+        require!(
+            env::predecessor_account_id() == env::current_account_id()
+                || env::predecessor_account_id() == self.fee_treasury_id,
+            "ERR_FORBIDDEN"
+        );
+
+        let amount = self.pending_fees;
+
+        require!(amount > 0, "ERR_NO_FEES_TO_WITHDRAW");
+
+        self.pending_fees = 0;
+
+        Event::FeeWithdrawal(vec![FeeWithdrawalData {
+            receiver_id: self.fee_treasury_id.clone(),
+            amount: U128(amount),
+        }])
+        .emit();
+
+        create_withdraw_fees_promise(&self.fee_treasury_id, amount)
+    }
+
+    pub fn get_fees(&self) -> JsonFees {
+        JsonFees {
+            fee_bps: self.fee_bps,
+            treasury_id: self.fee_treasury_id.clone(),
+            pending: U128(self.pending_fees),
+        }
+    }
+
+    pub fn get_quest_fees(&self, quest_id: QuestId) -> JsonQuestFees {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        JsonQuestFees {
+            quest_id,
+            fee_bps: self.internal_fee_bps(&quest),
+            collected: U128(self.fees_per_quest.get(&quest_id).unwrap_or(0)),
+        }
+    }
+}
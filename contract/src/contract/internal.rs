@@ -1,15 +1,29 @@
+use crate::contract::json::{JsonPool, JsonPoolRewards};
 use crate::contract::pools::Pool;
-use crate::contract::types::{ BoxId, Capacity, PoolId, Probability, TokenId };
-use near_sdk::{ env, require, AccountId };
+use crate::contract::types::{
+    BoxId, BundledReward, Capacity, PoolId, Probability, QuestId, Reward, TokenId, VestingPosition,
+    Weight,
+};
+use near_sdk::{env, require, AccountId, Balance, Promise, StorageUsage};
 
+use std::collections::HashSet;
 use std::str::FromStr;
 
 use super::enums::Network;
 use super::quest::Quest;
 use super::types::{ BoxRarity, BoxStatus };
 
-fn get_random_number(shift_amount: usize) -> u64 {
+// the random seed is the same for the whole receipt, so boxes opened together get their own one
+fn get_box_seed(quest_id: QuestId, box_id: BoxId) -> Vec<u8> {
     let mut seed = env::random_seed();
+
+    seed.extend_from_slice(&quest_id.to_le_bytes());
+    seed.extend_from_slice(&box_id.to_le_bytes());
+
+    env::sha256(&seed)
+}
+
+fn get_random_number(mut seed: Vec<u8>, shift_amount: usize) -> u64 {
     let seed_len = seed.len();
 
     let mut arr: [u8; 8] = Default::default();
@@ -20,6 +34,34 @@ fn get_random_number(shift_amount: usize) -> u64 {
     u64::from_le_bytes(arr)
 }
 
+// wide enough to cover any range of yocto amounts, the lower half matches get_random_number
+fn get_random_u128(mut seed: Vec<u8>, shift_amount: usize) -> u128 {
+    let seed_len = seed.len();
+
+    let mut arr: [u8; 16] = Default::default();
+
+    seed.rotate_left(shift_amount % seed_len);
+    arr.copy_from_slice(&seed[..16]);
+
+    u128::from_le_bytes(arr)
+}
+
+/// Claims are confirmed with at least one yocto, the deposit also covers the storage the claim takes
+pub(crate) fn charge_claim_storage(storage_used: StorageUsage) {
+    let storage_deposit = (env::storage_byte_cost() * storage_used as u128).max(1);
+
+    assert!(
+        env::attached_deposit() >= storage_deposit,
+        "Deposited amount must be bigger than {} yocto",
+        storage_deposit
+    );
+
+    let refund = env::attached_deposit() - storage_deposit;
+    if refund > 1 {
+        Promise::new(env::predecessor_account_id()).transfer(refund);
+    }
+}
+
 pub(crate) fn get_registry_iah_contract() -> AccountId {
     let network = Network::from(env::current_account_id());
 
@@ -39,74 +81,279 @@ pub(crate) fn get_issuer_iah_contract() -> AccountId {
 }
 
 impl Quest {
+    pub(crate) fn internal_insert_pool(&mut self, pool: &Pool) {
+        self.assert_rarity(&pool.rarity);
+
+        self.pools.insert(&pool.id, pool);
+
+        let mut pool_ids = self
+            .pool_ids_by_rarity
+            .get(&pool.rarity)
+            .unwrap_or_default();
+        pool_ids.insert(pool.id);
+        self.pool_ids_by_rarity.insert(&pool.rarity, &pool_ids);
+    }
+
+    pub(crate) fn internal_remove_pool(&mut self, pool: &Pool) {
+        self.pools.remove(&pool.id);
+
+        let mut pool_ids = self
+            .pool_ids_by_rarity
+            .get(&pool.rarity)
+            .unwrap_or_default();
+        pool_ids.remove(&pool.id);
+        self.pool_ids_by_rarity.insert(&pool.rarity, &pool_ids);
+    }
+
     pub(crate) fn internal_add_nft_pool(
         &mut self,
         rarity: BoxRarity,
         contract_id: AccountId,
-        token_id: TokenId
+        token_id: TokenId,
+        token_weight: Option<Weight>,
+        weight: Option<Weight>,
+        sponsor_id: Option<AccountId>,
     ) {
         // to ensure tokens within the contract and rarity will be in the same pool
-        let key = vec![contract_id.to_owned().to_string(), rarity.to_owned().to_string()].join(":");
+        let mut key_parts = vec![
+            contract_id.to_owned().to_string(),
+            rarity.to_owned().to_string(),
+        ];
+
+        // tokens of each sponsor are kept apart to refund them later
+        if let Some(sponsor_id) = sponsor_id.as_ref() {
+            key_parts.push(sponsor_id.to_string());
+        }
 
-        let pool = match self.nft_pool_by_key.get(&key) {
+        let key = key_parts.join(":");
+
+        let mut pool = match self.nft_pool_by_key.get(&key) {
             Option::None => {
                 let pool_id = self.next_pool_id.clone();
 
                 self.next_pool_id += 1;
 
                 let mut pool = Pool::create_nft_pool(pool_id, rarity, contract_id);
-                pool.add_nft_token(token_id);
+                pool.sponsor_id = sponsor_id;
+                pool.add_nft_token(token_id, token_weight);
 
                 pool
             }
             Option::Some(pool_id) => {
                 let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
-                pool.add_nft_token(token_id);
+                pool.add_nft_token(token_id, token_weight);
 
                 pool
             }
         };
 
-        self.pools.insert(&pool.id, &pool);
+        // weight isn't reset when it's omitted for the existing pool
+        if weight.is_some() {
+            pool.set_weight(weight);
+        }
 
-        let mut pool_ids = self.pool_ids_by_rarity.get(&rarity).unwrap_or_default();
-        pool_ids.insert(pool.id.clone());
-        self.pool_ids_by_rarity.insert(&rarity, &pool_ids);
+        // existing pool might be a part of some bundle, so it shouldn't be listed again
+        match self.nft_pool_by_key.get(&key) {
+            Option::None => self.internal_insert_pool(&pool),
+            Option::Some(_) => {
+                self.pools.insert(&pool.id, &pool);
+            }
+        };
 
         self.nft_pool_by_key.insert(&key, &pool.id);
     }
 
-    pub(crate) fn internal_claim(&mut self, box_id: BoxId) -> PoolId {
+    pub(crate) fn internal_add_bundle_pool(
+        &mut self,
+        rarity: BoxRarity,
+        pool_ids: Vec<PoolId>,
+        weight: Option<Weight>,
+    ) {
+        require!(pool_ids.len() > 1, "Bundle must contain at least 2 pools");
+
+        let unique_pool_ids: HashSet<PoolId> = pool_ids.iter().cloned().collect();
+        require!(
+            unique_pool_ids.len() == pool_ids.len(),
+            "ERR_DUPLICATED_POOLS"
+        );
+
+        pool_ids.iter().for_each(|pool_id| {
+            let pool = self.pools.get(pool_id).expect("ERR_POOL_NOT_FOUND");
+
+            require!(
+                pool.bundled_pool_ids().is_none(),
+                "Bundle can't contain another bundle"
+            );
+
+            // rewards of bundled pools are paid out together, so a schedule would be skipped
+            require!(pool.vesting.is_none(), "Bundle can't contain a vested pool");
+
+            let mut rarity_pool_ids = self
+                .pool_ids_by_rarity
+                .get(&pool.rarity)
+                .unwrap_or_default();
+
+            // the pool is hidden from the rarity, so its rewards can only be taken as a part of the bundle
+            require!(
+                rarity_pool_ids.remove(pool_id),
+                format!("Pool {} is already a part of some bundle", pool_id)
+            );
+
+            self.pool_ids_by_rarity
+                .insert(&pool.rarity, &rarity_pool_ids);
+        });
+
+        let pool_id = self.next_pool_id;
+
+        self.next_pool_id += 1;
+
+        let mut pool = Pool::create_bundle_pool(pool_id, rarity, pool_ids);
+
+        pool.set_weight(weight);
+
+        self.internal_insert_pool(&pool);
+    }
+
+    pub(crate) fn internal_pool_availability(&self, pool: &Pool) -> Capacity {
+        match pool.bundled_pool_ids() {
+            Option::None => pool.availability(),
+            Option::Some(pool_ids) => pool_ids
+                .iter()
+                .map(|pool_id| {
+                    self.pools
+                        .get(pool_id)
+                        .map(|bundled_pool| bundled_pool.availability())
+                        .unwrap_or(0)
+                })
+                .min()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Empty pools are never picked, otherwise the owner-defined weight takes precedence over availability
+    pub(crate) fn internal_pool_weight(&self, pool: &Pool) -> Weight {
+        let availability = self.internal_pool_availability(pool);
+
+        match availability {
+            0 => 0,
+            _ => pool.weight.unwrap_or(availability),
+        }
+    }
+
+    pub(crate) fn internal_pool_to_json(&self, pool: Pool) -> JsonPool {
+        match pool.bundled_pool_ids() {
+            Option::None => pool.into(),
+            Option::Some(pool_ids) => JsonPool {
+                pool_id: pool.id,
+                sponsor_id: pool.sponsor_id.clone(),
+                vesting: None,
+                rewards: JsonPoolRewards::Bundle {
+                    rewards: pool_ids
+                        .iter()
+                        .filter_map(|pool_id| self.pools.get(pool_id))
+                        .map(|bundled_pool| bundled_pool.into())
+                        .collect(),
+                    available: self.internal_pool_availability(&pool),
+                },
+            },
+        }
+    }
+
+    fn internal_take_reward(&mut self, pool: &mut Pool, random_number: u128) -> Reward {
+        match pool.bundled_pool_ids() {
+            Option::None => pool.take_reward_from_pool(random_number),
+            Option::Some(pool_ids) => {
+                let rewards = pool_ids
+                    .iter()
+                    .map(|pool_id| {
+                        let mut bundled_pool = self.pools.get(pool_id).expect("ERR_POOL_NOT_FOUND");
+
+                        let reward = bundled_pool.take_reward_from_pool(random_number);
+
+                        self.pools.insert(&bundled_pool.id, &bundled_pool);
+
+                        BundledReward {
+                            pool_id: bundled_pool.id,
+                            reward,
+                        }
+                    })
+                    .collect();
+
+                Reward::Bundle { rewards }
+            }
+        }
+    }
+
+    fn internal_put_reward(&mut self, pool_id: PoolId, reward: Reward) {
+        match reward {
+            Reward::Bundle { rewards } => {
+                rewards.into_iter().for_each(|bundled_reward| {
+                    self.internal_put_reward(bundled_reward.pool_id, bundled_reward.reward);
+                });
+            }
+            _ => {
+                let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+                pool.put_reward_to_pool(reward);
+
+                self.pools.insert(&pool.id, &pool);
+            }
+        }
+    }
+
+    pub(crate) fn internal_return_nft_token(&mut self, pool_id: PoolId, token_id: TokenId) {
+        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+        pool.return_nft_token(token_id);
+
+        self.pools.insert(&pool.id, &pool);
+    }
+
+    /// Returns the pool of the reward along with the storage taken by its vesting position
+    pub(crate) fn internal_claim(
+        &mut self,
+        box_id: BoxId,
+        account_id: &AccountId,
+    ) -> (PoolId, StorageUsage) {
         let mut box_data = self.boxes.get(&box_id).expect("ERR_BOX_NOT_FOUND");
 
+        require!(
+            box_data.box_status != BoxStatus::Expired
+                && !box_data.is_expired(env::block_timestamp()),
+            "ERR_BOX_EXPIRED"
+        );
         require!(box_data.box_status == BoxStatus::NonClaimed, "ERR_BOX_ALREADY_CLAIMED");
 
         // take reward of some rarity
-        let available_pools = self.pool_ids_by_rarity
+        let weighted_pools = self
+            .pool_ids_by_rarity
             .get(&box_data.box_rarity)
             .unwrap_or_default()
             .iter()
             .filter_map(|pool_id| {
                 let pool = self.pools.get(pool_id).unwrap();
+                let weight = self.internal_pool_weight(&pool);
 
-                (!pool.is_empty()).then_some(pool)
+                (weight > 0).then_some((pool, weight))
             })
-            .collect::<Vec<Pool>>();
+            .collect::<Vec<(Pool, Weight)>>();
 
-        require!(available_pools.len() > 0, "ERR_NO_POOLS_AVAILABLE");
+        require!(!weighted_pools.is_empty(), "ERR_NO_POOLS_AVAILABLE");
 
-        let total_available: Capacity = available_pools
+        // weights are summed up as u128 to not overflow
+        let total_weight: u128 = weighted_pools
             .iter()
-            .map(|pool| pool.availability())
+            .map(|(_, weight)| *weight as u128)
             .sum();
 
-        let random_number = get_random_number(0);
+        let seed = get_box_seed(self.id, box_id);
+        let random_number = get_random_number(seed.clone(), 0);
 
-        // bring to range [0, total_available - 1]
-        let random_in_range = random_number % total_available;
+        // bring to range [0, total_weight - 1]
+        let random_in_range = (random_number as u128) % total_weight;
 
-        let mut last: u64 = 0;
-        let mut pool_iterator = available_pools.iter();
+        let mut last: u128 = 0;
+        let mut pool_iterator = weighted_pools.into_iter();
 
         let mut random_pool = loop {
             let next_pool = pool_iterator.next();
@@ -114,13 +361,13 @@ impl Quest {
             // should never panic (Reward pools are over before a random one had been found)
             require!(next_pool.is_some(), "ERR_LOGIC");
 
-            let pool = next_pool.unwrap().to_owned();
+            let (pool, weight) = next_pool.unwrap();
+
+            last += weight as u128;
 
-            if random_in_range <= pool.availability() + last - 1 {
+            if random_in_range < last {
                 break pool;
             }
-
-            last += pool.availability().clone();
         };
 
         let probability = self.probability_by_rarity
@@ -133,32 +380,111 @@ impl Quest {
 
         let is_rewarded = threshold == u8::MAX || (threshold != u8::MIN && random < threshold);
 
-        match is_rewarded {
+        let vesting_storage = match is_rewarded {
             true => {
-                let reward = random_pool.take_reward_from_pool();
+                let reward = self.internal_take_reward(&mut random_pool, get_random_u128(seed, 8));
+
+                // bundled pools are taken as a whole, so only the vesting of the picked pool matters
+                let vesting_storage = match (random_pool.vesting.clone(), &reward) {
+                    (Some(schedule), Reward::Near { amount }) => {
+                        let position = VestingPosition {
+                            box_id,
+                            amount: amount.to_owned(),
+                            withdrawn: 0,
+                            start: env::block_timestamp(),
+                            schedule,
+                        };
+
+                        self.internal_add_vesting_position(account_id, position)
+                    }
+                    _ => 0,
+                };
 
                 box_data.box_status = BoxStatus::Claimed {
                     reward: Some(reward),
                 };
 
                 self.pools.insert(&random_pool.id, &random_pool);
+
+                vesting_storage
             }
             false => {
                 box_data.box_status = BoxStatus::Claimed { reward: None };
+
+                0
             }
-        }
+        };
 
         self.boxes.insert(&box_data.box_id, &box_data);
 
-        random_pool.id
+        (random_pool.id, vesting_storage)
+    }
+
+    /// Returns the storage taken by the position, the claimer pays for it
+    pub(crate) fn internal_add_vesting_position(
+        &mut self,
+        account_id: &AccountId,
+        position: VestingPosition,
+    ) -> StorageUsage {
+        let storage_used_before = env::storage_usage();
+
+        let mut positions = self.vesting_positions.get(account_id).unwrap_or_default();
+
+        positions.push(position);
+
+        self.vesting_positions.insert(account_id, &positions);
+
+        env::storage_usage() - storage_used_before
+    }
+
+    pub(crate) fn internal_is_vested(&self, account_id: &AccountId, box_id: BoxId) -> bool {
+        self.vesting_positions
+            .get(account_id)
+            .unwrap_or_default()
+            .iter()
+            .any(|position| position.box_id == box_id)
+    }
+
+    // the transfer has failed, so the amounts are available for the next withdrawal again
+    pub(crate) fn internal_revert_vested_withdrawal(
+        &mut self,
+        account_id: &AccountId,
+        withdrawn: &[(BoxId, Balance)],
+    ) {
+        let mut positions = self.vesting_positions.get(account_id).unwrap_or_default();
+
+        positions.iter_mut().for_each(|position| {
+            withdrawn
+                .iter()
+                .filter(|(box_id, _)| box_id == &position.box_id)
+                .for_each(|(_, amount)| {
+                    position.withdrawn -= amount;
+                });
+        });
+
+        self.vesting_positions.insert(account_id, &positions);
+    }
+
+    pub(crate) fn internal_remove_withdrawn_vesting_positions(&mut self, account_id: &AccountId) {
+        let positions = self
+            .vesting_positions
+            .get(account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|position| !position.is_withdrawn())
+            .collect::<Vec<VestingPosition>>();
+
+        match positions.is_empty() {
+            true => self.vesting_positions.remove(account_id),
+            false => self.vesting_positions.insert(account_id, &positions),
+        };
     }
 
     pub(crate) fn internal_undo_claim(&mut self, box_id: BoxId, pool_id: PoolId) {
         let mut box_data = self.boxes.get(&box_id).expect("ERR_BOX_NOT_FOUND");
-        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
 
         let reward_or_nothing = match box_data.box_status {
-            BoxStatus::NonClaimed => unreachable!(),
+            BoxStatus::NonClaimed | BoxStatus::Expired => unreachable!(),
             BoxStatus::Claimed { reward } => reward.to_owned(),
         };
 
@@ -166,8 +492,46 @@ impl Quest {
         self.boxes.insert(&box_data.box_id, &box_data);
 
         if let Some(reward) = reward_or_nothing {
-            pool.put_reward_to_pool(reward);
-            self.pools.insert(&pool.id, &pool);
+            self.internal_put_reward(pool_id, reward);
         }
     }
+
+    // puts back only the failed parts of a bundle, whatever was transferred stays claimed
+    pub(crate) fn internal_undo_claim_partially(
+        &mut self,
+        box_id: BoxId,
+        failed_parts: &[usize],
+    ) -> Reward {
+        let mut box_data = self.boxes.get(&box_id).expect("ERR_BOX_NOT_FOUND");
+
+        let rewards = match box_data.box_status {
+            BoxStatus::Claimed {
+                reward: Some(Reward::Bundle { rewards }),
+            } => rewards,
+            _ => unreachable!(),
+        };
+
+        let (failed_rewards, transferred_rewards): (Vec<_>, Vec<_>) = rewards
+            .into_iter()
+            .enumerate()
+            .partition(|(index, _)| failed_parts.contains(index));
+
+        failed_rewards.into_iter().for_each(|(_, bundled_reward)| {
+            self.internal_put_reward(bundled_reward.pool_id, bundled_reward.reward);
+        });
+
+        let reward = Reward::Bundle {
+            rewards: transferred_rewards
+                .into_iter()
+                .map(|(_, bundled_reward)| bundled_reward)
+                .collect(),
+        };
+
+        box_data.box_status = BoxStatus::Claimed {
+            reward: Some(reward.clone()),
+        };
+        self.boxes.insert(&box_data.box_id, &box_data);
+
+        reward
+    }
 }
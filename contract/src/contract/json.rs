@@ -1,8 +1,86 @@
-use near_sdk::{ json_types::U128, require, serde::{ Deserialize, Serialize }, AccountId };
+use std::collections::BTreeMap;
 
-use crate::contract::types::{ Capacity, Reward, TokenId };
+use near_sdk::{
+    borsh::BorshSerialize,
+    json_types::{Base64VecU8, U128, U64},
+    require,
+    serde::{Deserialize, Serialize},
+    AccountId,
+};
 
-use super::types::{ BoxId, BoxRarity, BoxStatus, QuestId };
+use crate::contract::types::{
+    BoxMedia, Capacity, PendingReward, Probability, Reward, TokenId, VestingPosition,
+    VestingSchedule,
+};
+use crate::contract::types::token_metadata::TokenMetadata;
+
+use super::types::{BasisPoints, BoxId, BoxRarity, BoxStatus, PoolId, QuestId};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonFees {
+    pub fee_bps: BasisPoints,
+    pub treasury_id: AccountId,
+    pub pending: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonQuestFees {
+    pub quest_id: QuestId,
+    // either the quest override or the default fee
+    pub fee_bps: BasisPoints,
+    pub collected: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonBoxSale {
+    pub rarity: BoxRarity,
+    pub price: U128,
+    pub supply: Option<U64>,
+    pub sold: U64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonBoxCapacity {
+    pub rarity: BoxRarity,
+    pub minted: U64,
+    pub max_boxes: Option<U64>,
+    // boxes that can still be minted under the caps, None if uncapped
+    pub remaining: Option<U64>,
+    // rewards left in the pools of the rarity
+    pub funded: U64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonRarityTier {
+    pub rarity: BoxRarity,
+    // IPFS CID of the box image, only optional for rare, epic and legendary
+    pub media: Option<String>,
+    pub probability: Option<Probability>,
+}
+
+// chance of the pool to be picked is weight / total_weight
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonPoolOdds {
+    pub pool_id: PoolId,
+    pub weight: U64,
+    pub total_weight: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonPool {
+    pub pool_id: PoolId,
+    pub sponsor_id: Option<AccountId>,
+    pub vesting: Option<VestingSchedule>,
+    #[serde(flatten)]
+    pub rewards: JsonPoolRewards,
+}
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "near_sdk::serde", tag = "kind", rename_all = "snake_case")]
@@ -12,11 +90,41 @@ pub enum JsonPoolRewards {
         available: Capacity,
         total: Capacity,
     },
+    NearRange {
+        min_amount: U128,
+        max_amount: U128,
+        available: U128,
+        total: U128,
+    },
     NonFungibleToken {
         contract_id: AccountId,
         token_ids: Vec<TokenId>,
+        token_weights: BTreeMap<TokenId, U64>,
         total: Capacity,
     },
+    FungibleToken {
+        contract_id: AccountId,
+        amount: U128,
+        available: Capacity,
+        total: Capacity,
+    },
+    NonFungibleTokenMint {
+        contract_id: AccountId,
+        metadata: Box<TokenMetadata>,
+        available: Capacity,
+        total: Capacity,
+    },
+    MultiToken {
+        contract_id: AccountId,
+        amount: U128,
+        balances: BTreeMap<TokenId, U128>,
+        available: Capacity,
+        total: Capacity,
+    },
+    Bundle {
+        rewards: Vec<JsonPool>,
+        available: Capacity,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -29,6 +137,18 @@ pub enum JsonReward {
         contract_id: AccountId,
         token_id: TokenId,
     },
+    FungibleToken {
+        contract_id: AccountId,
+        amount: U128,
+    },
+    MultiToken {
+        contract_id: AccountId,
+        token_id: TokenId,
+        amount: U128,
+    },
+    Bundle {
+        rewards: Vec<JsonReward>,
+    },
     Nothing,
 }
 
@@ -44,6 +164,35 @@ impl From<Reward> for JsonReward {
                     contract_id,
                     token_id,
                 },
+            Reward::FungibleToken { contract_id, amount } =>
+                Self::FungibleToken {
+                    contract_id,
+                    amount: amount.into(),
+                },
+            // minted token doesn't differ from the transferred one for the box owner
+            Reward::NonFungibleTokenMint {
+                contract_id,
+                token_id,
+                ..
+            } => Self::NonFungibleToken {
+                contract_id,
+                token_id,
+            },
+            Reward::MultiToken {
+                contract_id,
+                token_id,
+                amount,
+            } => Self::MultiToken {
+                contract_id,
+                token_id,
+                amount: amount.into(),
+            },
+            Reward::Bundle { rewards } => Self::Bundle {
+                rewards: rewards
+                    .into_iter()
+                    .map(|bundled_reward| bundled_reward.reward.into())
+                    .collect(),
+            },
         }
     }
 }
@@ -57,6 +206,48 @@ impl From<Option<Reward>> for JsonReward {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonVestingPosition {
+    pub box_id: BoxId,
+    pub amount: U128,
+    pub withdrawn: U128,
+    pub withdrawable: U128,
+    pub start: U64,
+    pub schedule: VestingSchedule,
+}
+
+impl JsonVestingPosition {
+    pub fn new(position: VestingPosition, now: u64) -> Self {
+        Self {
+            box_id: position.box_id,
+            amount: U128(position.amount),
+            withdrawn: U128(position.withdrawn),
+            withdrawable: U128(position.withdrawable(now)),
+            start: U64(position.start),
+            schedule: position.schedule,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonPendingReward {
+    pub quest_id: QuestId,
+    pub box_id: BoxId,
+    pub reward: JsonReward,
+}
+
+impl From<PendingReward> for JsonPendingReward {
+    fn from(value: PendingReward) -> Self {
+        Self {
+            quest_id: value.quest_id,
+            box_id: value.box_id,
+            reward: value.reward.into(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde", tag = "kind", rename_all = "snake_case")]
 pub enum JsonBoxStatus {
@@ -64,6 +255,7 @@ pub enum JsonBoxStatus {
         reward: JsonReward,
     },
     NonClaimed,
+    Expired,
 }
 
 impl Into<JsonBoxStatus> for BoxStatus {
@@ -74,6 +266,7 @@ impl Into<JsonBoxStatus> for BoxStatus {
                     reward: reward.into(),
                 },
             BoxStatus::NonClaimed => JsonBoxStatus::NonClaimed,
+            BoxStatus::Expired => JsonBoxStatus::Expired,
         }
     }
 }
@@ -86,6 +279,8 @@ pub struct JsonBox {
     pub box_rarity: BoxRarity,
     pub box_status: JsonBoxStatus,
     pub ipfs: String,
+    pub media_hash: Option<Base64VecU8>,
+    pub expires_at: Option<U64>,
 }
 
 impl JsonBox {
@@ -93,18 +288,78 @@ impl JsonBox {
         quest_id: QuestId,
         box_id: BoxId,
         box_rarity: BoxRarity,
-        box_status: JsonBoxStatus
+        box_status: JsonBoxStatus,
+        media: BoxMedia,
+        expires_at: Option<U64>,
     ) -> Self {
         Self {
             quest_id,
             box_id,
+            ipfs: media.media,
+            media_hash: media.media_hash,
             box_rarity,
             box_status,
-            ipfs: box_rarity.to_media_ipfs(),
+            expires_at,
         }
     }
 }
 
+// https://nomicon.io/Standards/Tokens/NonFungibleToken/Core
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonToken {
+    pub token_id: TokenId,
+    pub owner_id: AccountId,
+    pub metadata: Option<TokenMetadata>,
+}
+
+// https://nomicon.io/Standards/Tokens/NonFungibleToken/Metadata
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonNftContractMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+    pub icon: Option<String>,
+    pub base_uri: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<Base64VecU8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonNftMint {
+    pub contract_id: AccountId,
+    pub metadata: TokenMetadata,
+    pub deposit: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct JsonMintVoucher {
+    pub quest_id: QuestId,
+    pub rarity: BoxRarity,
+    pub receiver_id: AccountId,
+    // picked by the quest owner, every nonce is redeemed once
+    pub nonce: U64,
+    pub expires_at: U64,
+}
+
+impl JsonMintVoucher {
+    /// Bytes signed by the voucher key, the borsh serialized (quest_id, rarity, receiver_id, nonce, expires_at)
+    pub fn to_message(&self) -> Vec<u8> {
+        (
+            self.quest_id,
+            self.rarity.clone(),
+            self.receiver_id.clone(),
+            self.nonce.0,
+            self.expires_at.0,
+        )
+            .try_to_vec()
+            .unwrap()
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Pagination {
@@ -1,2 +1,4 @@
 pub mod json_quest;
-pub mod json_nft_message;
\ No newline at end of file
+pub mod json_nft_message;
+pub mod json_ft_message;
+pub mod json_mt_message;
\ No newline at end of file
@@ -0,0 +1,15 @@
+use crate::contract::types::{BoxRarity, QuestId};
+
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtOnTransferMessage {
+    pub rarity: BoxRarity,
+    pub quest_id: QuestId,
+    pub amount: U128,
+    pub capacity: U64,
+    #[serde(default)]
+    pub weight: Option<U64>,
+}
@@ -1,5 +1,6 @@
 use crate::contract::types::{BoxRarity, QuestId};
 
+use near_sdk::json_types::U64;
 use near_sdk::serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -7,4 +8,9 @@ use near_sdk::serde::{Deserialize, Serialize};
 pub struct NftOnTransferMessage {
     pub rarity: BoxRarity,
     pub quest_id: QuestId,
+    // sub-rarity weight of the token within its pool
+    #[serde(default)]
+    pub token_weight: Option<U64>,
+    #[serde(default)]
+    pub weight: Option<U64>,
 }
\ No newline at end of file
@@ -0,0 +1,134 @@
+use near_sdk::{env, near_bindgen, require, AccountId, Balance, Promise, PublicKey, ONE_NEAR};
+
+use crate::{Contract, ContractExt};
+
+use super::json::JsonBox;
+use super::types::questbox_data::QuestBoxData;
+use super::types::{BoxId, BoxRarity, QuestId};
+
+// pays for the gas of claim_keyed transactions signed with the box key, the contract can't read
+// how much of it is left, so it's spent as a whole and never refunded
+const KEYED_BOX_ALLOWANCE: Balance = ONE_NEAR / 10;
+const CLAIM_KEYED_METHOD_NAME: &str = "claim_keyed";
+
+impl Contract {
+    /// Removes the key of a keyed box, if the box has one
+    pub(crate) fn internal_remove_box_key(&mut self, questbox_data: &QuestBoxData) {
+        if let Some(public_key) = self.keyed_box_keys.remove(questbox_data) {
+            self.keyed_boxes.remove(&public_key);
+
+            Promise::new(env::current_account_id()).delete_key(public_key);
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Mints a box held by the contract, which can be claimed by anyone who has the private key
+    #[payable]
+    pub fn mint_keyed(
+        &mut self,
+        quest_id: QuestId,
+        public_key: PublicKey,
+        rarity: BoxRarity,
+    ) -> BoxId {
+        require!(
+            !self.keyed_boxes.contains_key(&public_key),
+            "ERR_KEY_ALREADY_EXISTS"
+        );
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        let questbox = quest.mint(env::current_account_id(), rarity);
+        self.quests.insert(&quest.id, &quest);
+
+        self.mint_boxes_per_owner(&questbox);
+
+        let questbox_data = QuestBoxData::new(questbox.quest_id, questbox.box_id);
+
+        self.keyed_boxes.insert(&public_key, &questbox_data);
+        self.keyed_box_keys.insert(&questbox_data, &public_key);
+
+        let storage_used_after = env::storage_usage();
+
+        let deposit = env::storage_byte_cost()
+            * ((storage_used_after - storage_used_before) as u128)
+            + KEYED_BOX_ALLOWANCE;
+
+        assert!(
+            env::attached_deposit() >= deposit,
+            "Deposited amount must be bigger than {} yocto",
+            deposit
+        );
+
+        let refund = env::attached_deposit() - deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+
+        Promise::new(env::current_account_id()).add_access_key(
+            public_key,
+            KEYED_BOX_ALLOWANCE,
+            env::current_account_id(),
+            CLAIM_KEYED_METHOD_NAME.to_string(),
+        );
+
+        questbox.box_id
+    }
+
+    /// Signed with the box key, passes the box to the receiver and claims it, the key is removed afterwards
+    pub fn claim_keyed(&mut self, receiver_id: AccountId) -> Promise {
+        require!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "ERR_FORBIDDEN"
+        );
+
+        let public_key = env::signer_account_pk();
+
+        let questbox_data = self
+            .keyed_boxes
+            .get(&public_key)
+            .expect("ERR_KEY_NOT_FOUND");
+
+        self.internal_remove_box_key(&questbox_data);
+
+        self.internal_transfer_box(
+            &env::current_account_id(),
+            &receiver_id,
+            &questbox_data,
+            None,
+        );
+
+        let mut quest = self.quests.get(&questbox_data.quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!(
+                "Quest with id {} wasn't found",
+                questbox_data.quest_id
+            ))
+        });
+
+        // the key can't attach a deposit, so the vesting and escrow entries of the claim are
+        // covered by the contract
+        let (promise, _) = quest.claim_for(questbox_data.box_id, &receiver_id);
+
+        if quest.escrow_enabled {
+            self.internal_escrow_rewards(&quest, &receiver_id, &[questbox_data.box_id]);
+        }
+
+        promise
+    }
+
+    pub fn keyed_box(&self, public_key: PublicKey) -> Option<JsonBox> {
+        let questbox_data = self.keyed_boxes.get(&public_key)?;
+
+        let quest = self.quests.get(&questbox_data.quest_id)?;
+
+        quest
+            .boxes
+            .get(&questbox_data.box_id)
+            .map(|questbox| quest.box_to_json(questbox))
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{BTreeSet, HashSet};
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, UnorderedSet};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::{Contract, ContractExt};
+
+use super::enums::StorageKey;
+use super::pools::{NearPoolKind, NonFungibleTokenPoolKind, Pool};
+use super::quest::Quest;
+use super::questbox::QuestBox;
+use super::trusted_contracts::get_trusted_ft_contracts;
+use super::types::questbox_data::QuestBoxData;
+use super::types::{BoxId, BoxRarity, PoolId, Probability, QuestId, TokenId};
+
+// layouts of the first release, they are only read by the migration
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyContract {
+    pub quests: LookupMap<QuestId, LegacyQuest>,
+    pub quests_per_owner: LookupMap<AccountId, UnorderedSet<QuestId>>,
+    pub questboxes_per_owner: LookupMap<AccountId, UnorderedSet<QuestBoxData>>,
+    pub next_quest_id: QuestId,
+    pub trusted_nft_contracts: UnorderedSet<AccountId>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyQuest {
+    pub id: QuestId,
+    pub title: String,
+    pub owner_id: AccountId,
+    pub next_pool_id: PoolId,
+    pub pools: LookupMap<PoolId, LegacyPool>,
+    pub nft_pool_by_key: LookupMap<String, PoolId>,
+    pub pool_ids_by_rarity: LookupMap<BoxRarity, HashSet<PoolId>>,
+    pub next_box_id: BoxId,
+    // boxes of the first release are read as they are, see QuestBox
+    pub boxes: LookupMap<BoxId, QuestBox>,
+    pub probability_by_rarity: LookupMap<BoxRarity, Probability>,
+    pub users: UnorderedSet<AccountId>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyPool {
+    pub id: PoolId,
+    pub rarity: BoxRarity,
+    pub kind: LegacyPoolKind,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum LegacyPoolKind {
+    Near(NearPoolKind),
+    NonFungibleToken(LegacyNonFungibleTokenPoolKind),
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyNonFungibleTokenPoolKind {
+    pub contract_id: AccountId,
+    pub tokens: BTreeSet<TokenId>,
+    pub available_tokens: BTreeSet<TokenId>,
+}
+
+impl From<LegacyPool> for Pool {
+    fn from(pool: LegacyPool) -> Self {
+        match pool.kind {
+            LegacyPoolKind::Near(near_pool) => {
+                Pool::from_near_kind(pool.id, pool.rarity, near_pool)
+            }
+            LegacyPoolKind::NonFungibleToken(nft_pool) => Pool::from_nft_kind(
+                pool.id,
+                pool.rarity,
+                NonFungibleTokenPoolKind {
+                    contract_id: nft_pool.contract_id,
+                    tokens: nft_pool.tokens,
+                    available_tokens: nft_pool.available_tokens,
+                    token_weights: Default::default(),
+                },
+            ),
+        }
+    }
+}
+
+impl From<LegacyQuest> for Quest {
+    fn from(mut legacy_quest: LegacyQuest) -> Self {
+        // the collections of the quest are keyed by its id, so the new ones point to the same entries
+        let mut quest = Quest::new(legacy_quest.id, &legacy_quest.title, &legacy_quest.owner_id);
+
+        quest.next_pool_id = legacy_quest.next_pool_id;
+        quest.next_box_id = legacy_quest.next_box_id;
+        // the set keeps its length in the quest itself
+        quest.users = legacy_quest.users;
+        // quests of the first release had the three default rarities
+        quest.tiers = Quest::default_tiers();
+
+        // pools are never removed, but their layout changed, so the old entry is taken out before
+        // the new one is written to the same key
+        (0..legacy_quest.next_pool_id)
+            .filter_map(|pool_id| legacy_quest.pools.remove(&pool_id))
+            .for_each(|legacy_pool| {
+                let pool = Pool::from(legacy_pool);
+
+                quest.pools.insert(&pool.id, &pool);
+            });
+
+        // mint caps only count boxes minted from now on
+
+        quest
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Converts the state of the first release, the current state is returned as it is
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("ERR_NO_STATE");
+
+        if let Ok(contract) = Contract::try_from_slice(&state) {
+            return contract;
+        }
+
+        let mut legacy_contract =
+            LegacyContract::try_from_slice(&state).expect("ERR_UNKNOWN_STATE_LAYOUT");
+
+        let mut quests = LookupMap::new(StorageKey::Quests);
+        let mut questboxes = UnorderedSet::new(StorageKey::QuestBoxes);
+
+        (0..legacy_contract.next_quest_id)
+            .filter_map(|quest_id| legacy_contract.quests.remove(&quest_id))
+            .for_each(|legacy_quest| {
+                let quest = Quest::from(legacy_quest);
+
+                // boxes of the first release are indexed as tokens
+                (0..quest.next_box_id)
+                    .filter(|box_id| quest.boxes.contains_key(box_id))
+                    .for_each(|box_id| {
+                        questboxes.insert(&QuestBoxData::new(quest.id, box_id));
+                    });
+
+                quests.insert(&quest.id, &quest);
+            });
+
+        let mut contract = Self {
+            quests,
+            quests_per_owner: legacy_contract.quests_per_owner,
+            questboxes_per_owner: legacy_contract.questboxes_per_owner,
+            questboxes,
+            next_quest_id: legacy_contract.next_quest_id,
+            trusted_nft_contracts: legacy_contract.trusted_nft_contracts,
+            trusted_ft_contracts: UnorderedSet::new(StorageKey::TrustedFtContracts),
+            trusted_mt_contracts: UnorderedSet::new(StorageKey::TrustedMtContracts),
+            fee_bps: 0,
+            fee_treasury_id: env::current_account_id(),
+            fees_per_quest: LookupMap::new(StorageKey::FeesPerQuest),
+            pending_fees: 0,
+            pending_rewards: LookupMap::new(StorageKey::PendingRewards),
+            keyed_boxes: LookupMap::new(StorageKey::KeyedBoxes),
+            keyed_box_keys: LookupMap::new(StorageKey::KeyedBoxKeys),
+        };
+
+        get_trusted_ft_contracts()
+            .into_iter()
+            .for_each(|contract_id| contract.trust_ft_contract(contract_id));
+
+        contract
+    }
+}
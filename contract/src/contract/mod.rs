@@ -8,4 +8,12 @@ pub mod pools;
 pub mod types;
 pub mod enums;
 pub mod json_types;
-pub mod json;
\ No newline at end of file
+pub mod json;
+pub mod events;
+pub mod fees;
+pub mod escrow;
+pub mod nft;
+pub mod keyed_boxes;
+pub mod sales;
+pub mod vouchers;
+pub mod migration;
\ No newline at end of file
@@ -0,0 +1,310 @@
+use near_sdk::collections::UnorderedSet;
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, require, AccountId, Promise, PromiseOrValue};
+
+use crate::{Contract, ContractExt};
+
+use super::callbacks::create_nft_transfer_call_promise;
+use super::enums::StorageKey;
+use super::events::{NftEvent, NftTransferData};
+use super::json::{JsonNftContractMetadata, JsonToken};
+use super::quest::Quest;
+use super::questbox::QuestBox;
+use super::types::questbox_data::QuestBoxData;
+use super::types::token_metadata::TokenMetadata;
+use super::types::{BoxStatus, QuestId, TokenId};
+
+const NFT_METADATA_SPEC: &str = "nft-1.0.0";
+const NFT_NAME: &str = "Mystery Box";
+const NFT_SYMBOL: &str = "MBOX";
+const NFT_BASE_URI: &str = "https://ipfs.io/ipfs";
+
+const DEFAULT_TOKENS_LIMIT: u64 = 50;
+
+fn create_token(quest: &Quest, questbox: &QuestBox) -> JsonToken {
+    let questbox_data = QuestBoxData::new(questbox.quest_id, questbox.box_id);
+    let media = quest.rarity_media(&questbox.box_rarity);
+
+    JsonToken {
+        token_id: questbox_data.to_token_id(),
+        owner_id: questbox.owner_id.clone(),
+        metadata: Some(TokenMetadata {
+            title: Some(format!("{} #{}", quest.title, questbox.box_id)),
+            description: Some(format!("{} mystery box", questbox.box_rarity)),
+            media: Some(media.media),
+            media_hash: media.media_hash,
+            copies: Some(1),
+            issued_at: None,
+            // NEP-177 expects milliseconds
+            expires_at: questbox
+                .expires_at
+                .map(|expires_at| (expires_at / 1_000_000).to_string()),
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+        }),
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_add_questbox_to_owner(
+        &mut self,
+        owner_id: &AccountId,
+        questbox_data: &QuestBoxData,
+    ) {
+        let mut boxes_per_owner = self.questboxes_per_owner.get(owner_id).unwrap_or_else(|| {
+            let account_hash = env::sha256_array(owner_id.as_bytes());
+
+            UnorderedSet::new(StorageKey::QuestBoxesData { account_hash })
+        });
+
+        boxes_per_owner.insert(questbox_data);
+
+        self.questboxes_per_owner.insert(owner_id, &boxes_per_owner);
+    }
+
+    pub(crate) fn internal_remove_questbox_from_owner(
+        &mut self,
+        owner_id: &AccountId,
+        questbox_data: &QuestBoxData,
+    ) {
+        let mut boxes_per_owner = self
+            .questboxes_per_owner
+            .get(owner_id)
+            .expect("ERR_BOX_NOT_FOUND");
+
+        boxes_per_owner.remove(questbox_data);
+
+        self.questboxes_per_owner.insert(owner_id, &boxes_per_owner);
+    }
+
+    pub(crate) fn internal_owns_quest_boxes(
+        &self,
+        owner_id: &AccountId,
+        quest_id: QuestId,
+    ) -> bool {
+        self.questboxes_per_owner
+            .get(owner_id)
+            .map(|boxes_per_owner| boxes_per_owner.iter().any(|data| data.quest_id == quest_id))
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn assert_box_transferable(&self, questbox_data: &QuestBoxData) {
+        let quest = self.quests.get(&questbox_data.quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!(
+                "Quest with id {} wasn't found",
+                questbox_data.quest_id
+            ))
+        });
+
+        quest.assert_transfers_enabled();
+
+        let questbox = quest
+            .boxes
+            .get(&questbox_data.box_id)
+            .expect("ERR_BOX_NOT_FOUND");
+
+        // expired boxes can't be claimed, so they aren't passed on either
+        require!(
+            questbox.box_status != BoxStatus::Expired
+                && !questbox.is_expired(env::block_timestamp()),
+            "ERR_BOX_EXPIRED"
+        );
+    }
+
+    /// Transfers the box with the sender paying for the receiver's storage, the rest of the deposit is refunded
+    pub(crate) fn internal_transfer_box_with_deposit(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        questbox_data: &QuestBoxData,
+        memo: Option<String>,
+    ) -> QuestBox {
+        require!(
+            env::attached_deposit() > 0,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+
+        let storage_used_before = env::storage_usage();
+
+        let questbox = self.internal_transfer_box(sender_id, receiver_id, questbox_data, memo);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(sender_id.clone()).transfer(refund);
+        }
+
+        questbox
+    }
+
+    /// Moves the box to the receiver, so that only the new holder is able to claim it
+    pub(crate) fn internal_transfer_box(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        questbox_data: &QuestBoxData,
+        memo: Option<String>,
+    ) -> QuestBox {
+        let mut quest = self.quests.get(&questbox_data.quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!(
+                "Quest with id {} wasn't found",
+                questbox_data.quest_id
+            ))
+        });
+
+        let questbox = quest.transfer_box(questbox_data.box_id, sender_id, receiver_id);
+
+        self.internal_remove_questbox_from_owner(sender_id, questbox_data);
+        self.internal_add_questbox_to_owner(receiver_id, questbox_data);
+
+        if !self.internal_owns_quest_boxes(sender_id, quest.id) {
+            quest.users.remove(sender_id);
+        }
+
+        self.quests.insert(&quest.id, &quest);
+
+        NftEvent::NftTransfer(vec![NftTransferData {
+            old_owner_id: sender_id.clone(),
+            new_owner_id: receiver_id.clone(),
+            token_ids: vec![questbox_data.to_token_id()],
+            memo,
+        }])
+        .emit();
+
+        questbox
+    }
+}
+
+// https://nomicon.io/Standards/Tokens/NonFungibleToken/Core
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        #[allow(unused_variables)] approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        let questbox_data = QuestBoxData::from_token_id(&token_id).expect("ERR_INVALID_TOKEN_ID");
+
+        self.assert_box_transferable(&questbox_data);
+
+        self.internal_transfer_box_with_deposit(
+            &env::predecessor_account_id(),
+            &receiver_id,
+            &questbox_data,
+            memo,
+        );
+    }
+
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        #[allow(unused_variables)] approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        let questbox_data = QuestBoxData::from_token_id(&token_id).expect("ERR_INVALID_TOKEN_ID");
+
+        self.assert_box_transferable(&questbox_data);
+
+        let sender_id = env::predecessor_account_id();
+
+        self.internal_transfer_box_with_deposit(&sender_id, &receiver_id, &questbox_data, memo);
+
+        create_nft_transfer_call_promise(&sender_id, &receiver_id, &token_id, msg).into()
+    }
+
+    pub fn nft_token(&self, token_id: TokenId) -> Option<JsonToken> {
+        let questbox_data = QuestBoxData::from_token_id(&token_id)?;
+
+        let quest = self.quests.get(&questbox_data.quest_id)?;
+        let questbox = quest.boxes.get(&questbox_data.box_id)?;
+
+        Some(create_token(&quest, &questbox))
+    }
+
+    // https://nomicon.io/Standards/Tokens/NonFungibleToken/Metadata
+    pub fn nft_metadata(&self) -> JsonNftContractMetadata {
+        JsonNftContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: NFT_NAME.to_string(),
+            symbol: NFT_SYMBOL.to_string(),
+            icon: None,
+            base_uri: Some(NFT_BASE_URI.to_string()),
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    // https://nomicon.io/Standards/Tokens/NonFungibleToken/Enumeration
+    pub fn nft_total_supply(&self) -> U128 {
+        U128(self.questboxes.len() as u128)
+    }
+
+    // only the requested window of the index is read
+    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<JsonToken> {
+        let from_index = from_index.map(|index| index.0 as u64).unwrap_or(0);
+        let to_index = from_index
+            .saturating_add(limit.unwrap_or(DEFAULT_TOKENS_LIMIT))
+            .min(self.questboxes.len());
+
+        (from_index..to_index)
+            .filter_map(|index| {
+                let questbox_data = self.questboxes.as_vector().get(index)?;
+
+                let quest = self.quests.get(&questbox_data.quest_id)?;
+                let questbox = quest.boxes.get(&questbox_data.box_id)?;
+
+                Some(create_token(&quest, &questbox))
+            })
+            .collect()
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        self.questboxes_per_owner
+            .get(&account_id)
+            .map(|boxes_per_owner| U128(boxes_per_owner.len() as u128))
+            .unwrap_or(U128(0))
+    }
+
+    pub fn nft_tokens_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<JsonToken> {
+        let boxes_per_owner = match self.questboxes_per_owner.get(&account_id) {
+            Some(boxes_per_owner) => boxes_per_owner,
+            None => return Vec::new(),
+        };
+
+        boxes_per_owner
+            .iter()
+            .skip(from_index.map(|index| index.0 as usize).unwrap_or(0))
+            .take(limit.unwrap_or(DEFAULT_TOKENS_LIMIT) as usize)
+            .filter_map(|questbox_data| {
+                let quest = self.quests.get(&questbox_data.quest_id)?;
+                let questbox = quest.boxes.get(&questbox_data.box_id)?;
+
+                Some(create_token(&quest, &questbox))
+            })
+            .collect()
+    }
+}
@@ -1,7 +1,9 @@
-use std::collections::BTreeSet;
-use contract::json::JsonPoolRewards;
-use contract::types::{Capacity, PoolId, Reward, TokenId};
+use std::collections::{BTreeMap, BTreeSet};
+use contract::json::{JsonPool, JsonPoolRewards};
+use contract::types::token_metadata::TokenMetadata;
+use contract::types::{Capacity, PoolId, Reward, TokenId, VestingSchedule, Weight};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
 use near_sdk::require;
 use near_sdk::{AccountId, Balance};
 
@@ -11,6 +13,12 @@ use crate::*;
 pub struct Pool {
     pub id: PoolId,
     pub rarity: BoxRarity,
+    // overrides availability when the pool is being picked during the claim
+    pub weight: Option<Weight>,
+    // pools added by the quest owner don't have a sponsor
+    pub sponsor_id: Option<AccountId>,
+    // Near rewards of the pool are released over time instead of the transfer
+    pub vesting: Option<VestingSchedule>,
     kind: PoolKind,
 }
 
@@ -26,63 +34,355 @@ impl Pool {
         Self {
             id,
             rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
             kind: PoolKind::Near(near_pool),
         }
     }
 
+    pub fn create_near_range_pool(
+        id: PoolId,
+        rarity: BoxRarity,
+        min_amount: Balance,
+        max_amount: Balance,
+        budget: Balance,
+    ) -> Self {
+        let near_range_pool = NearRangePoolKind::new(min_amount, max_amount, budget);
+
+        Self {
+            id,
+            rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
+            kind: PoolKind::NearRange(near_range_pool),
+        }
+    }
+
+    pub fn create_nft_mint_pool(
+        id: PoolId,
+        rarity: BoxRarity,
+        contract_id: AccountId,
+        token_id_prefix: String,
+        metadata: TokenMetadata,
+        deposit: Balance,
+        capacity: Capacity,
+    ) -> Self {
+        let nft_mint_pool = NonFungibleTokenMintPoolKind::new(
+            contract_id,
+            token_id_prefix,
+            metadata,
+            deposit,
+            capacity,
+        );
+
+        Self {
+            id,
+            rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
+            kind: PoolKind::NonFungibleTokenMint(Box::new(nft_mint_pool)),
+        }
+    }
+
+    pub fn create_mt_pool(
+        id: PoolId,
+        rarity: BoxRarity,
+        contract_id: AccountId,
+        amount: Balance,
+        balances: BTreeMap<TokenId, Balance>,
+    ) -> Self {
+        let mt_pool = MultiTokenPoolKind::new(contract_id, amount, balances);
+
+        Self {
+            id,
+            rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
+            kind: PoolKind::MultiToken(mt_pool),
+        }
+    }
+
+    pub fn create_bundle_pool(id: PoolId, rarity: BoxRarity, pool_ids: Vec<PoolId>) -> Self {
+        Self {
+            id,
+            rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
+            kind: PoolKind::Bundle(BundlePoolKind { pool_ids }),
+        }
+    }
+
     pub fn create_nft_pool(id: PoolId, rarity: BoxRarity, contract_id: AccountId) -> Self {
         let nft_pool = NonFungibleTokenPoolKind::new(contract_id);
 
         Self {
             id,
             rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
             kind: PoolKind::NonFungibleToken(nft_pool),
         }
     }
 
+    // pools of the first release keep their balances and tokens when they are migrated
+    pub(crate) fn from_near_kind(id: PoolId, rarity: BoxRarity, near_pool: NearPoolKind) -> Self {
+        Self {
+            id,
+            rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
+            kind: PoolKind::Near(near_pool),
+        }
+    }
+
+    pub(crate) fn from_nft_kind(
+        id: PoolId,
+        rarity: BoxRarity,
+        nft_pool: NonFungibleTokenPoolKind,
+    ) -> Self {
+        Self {
+            id,
+            rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
+            kind: PoolKind::NonFungibleToken(nft_pool),
+        }
+    }
+
+    pub fn create_ft_pool(
+        id: PoolId,
+        rarity: BoxRarity,
+        contract_id: AccountId,
+        amount: Balance,
+        capacity: Capacity,
+    ) -> Self {
+        let ft_pool = FungibleTokenPoolKind::new(contract_id, amount, capacity);
+
+        Self {
+            id,
+            rarity,
+            weight: None,
+            sponsor_id: None,
+            vesting: None,
+            kind: PoolKind::FungibleToken(ft_pool),
+        }
+    }
+
+    // bundles don't hold rewards on their own, they take them from the bundled pools
+    pub fn set_weight(&mut self, weight: Option<Weight>) {
+        require!(weight != Some(0), "ERR_ZERO_WEIGHT");
+
+        self.weight = weight;
+    }
+
+    pub fn set_vesting(&mut self, vesting: Option<VestingSchedule>) {
+        match self.kind {
+            PoolKind::Near(_) | PoolKind::NearRange(_) => {}
+            _ => panic!("ERR_VESTING_NOT_SUPPORTED"),
+        };
+
+        if let Some(vesting) = vesting.as_ref() {
+            vesting.assert_valid();
+        }
+
+        self.vesting = vesting;
+    }
+
+    pub fn bundled_pool_ids(&self) -> Option<Vec<PoolId>> {
+        match self.kind {
+            PoolKind::Bundle(ref pool) => Some(pool.pool_ids.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_drained(&self) -> bool {
+        match self.kind {
+            PoolKind::Near(ref pool) => pool.capacity == 0,
+            PoolKind::NearRange(ref pool) => pool.budget == 0,
+            _ => false,
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         match self.kind {
             PoolKind::Near(ref pool) => pool.available == 0,
+            PoolKind::NearRange(ref pool) => pool.available_budget < pool.min_amount,
             PoolKind::NonFungibleToken(ref pool) => pool.available_tokens.len() == 0,
+            PoolKind::FungibleToken(ref pool) => pool.available == 0,
+            PoolKind::NonFungibleTokenMint(ref pool) => pool.available == 0,
+            PoolKind::MultiToken(ref pool) => pool.availability() == 0,
+            PoolKind::Bundle(_) => unreachable!(),
         }
     }
 
-    pub fn add_nft_token(&mut self, token_id: TokenId) {
+    pub fn add_nft_token(&mut self, token_id: TokenId, token_weight: Option<Weight>) {
         match self.kind {
-            PoolKind::Near(_) => unreachable!(),
-            PoolKind::NonFungibleToken(ref mut pool) => pool.add_token(token_id),
+            PoolKind::NonFungibleToken(ref mut pool) => pool.add_token(token_id, token_weight),
+            _ => unreachable!(),
+        };
+    }
+
+    pub fn return_nft_token(&mut self, token_id: TokenId) {
+        match self.kind {
+            PoolKind::NonFungibleToken(ref mut pool) => pool.return_token(token_id),
+            _ => unreachable!(),
         };
     }
 
     pub fn availability(&self) -> u64 {
         match self.kind {
             PoolKind::Near(ref pool) => pool.available,
+            PoolKind::NearRange(ref pool) => pool.availability(),
             PoolKind::NonFungibleToken(ref pool) => pool.available_tokens.len() as u64,
+            PoolKind::FungibleToken(ref pool) => pool.available,
+            PoolKind::NonFungibleTokenMint(ref pool) => pool.available,
+            PoolKind::MultiToken(ref pool) => pool.availability(),
+            PoolKind::Bundle(_) => unreachable!(),
+        }
+    }
+
+    pub fn top_up_near(&mut self, extra_capacity: Capacity) -> Balance {
+        match self.kind {
+            PoolKind::Near(ref mut pool) => pool.top_up(extra_capacity),
+            _ => panic!("ERR_NOT_NEAR_POOL"),
+        }
+    }
+
+    pub fn withdraw_near(&mut self, count: Capacity) -> Balance {
+        match self.kind {
+            PoolKind::Near(ref mut pool) => pool.withdraw(count),
+            _ => panic!("ERR_NOT_NEAR_POOL"),
+        }
+    }
+
+    pub fn withdraw_near_range(&mut self, amount: Balance) {
+        match self.kind {
+            PoolKind::NearRange(ref mut pool) => pool.withdraw(amount),
+            _ => panic!("ERR_NOT_NEAR_RANGE_POOL"),
+        }
+    }
+
+    /// Takes out everything that is still unused, the NFT tokens are limited by the amount per call
+    pub fn withdraw_unused(
+        &mut self,
+        limit: usize,
+    ) -> (Balance, Option<(AccountId, Vec<TokenId>)>) {
+        match self.kind {
+            PoolKind::Near(ref mut pool) => {
+                let amount = match pool.available {
+                    0 => 0,
+                    available => pool.withdraw(available),
+                };
+
+                (amount, None)
+            }
+            PoolKind::NearRange(ref mut pool) => {
+                let amount = pool.available_budget;
+
+                if amount > 0 {
+                    pool.withdraw(amount);
+                }
+
+                (amount, None)
+            }
+            PoolKind::NonFungibleToken(_) => (0, Some(self.withdraw_nft_tokens(None, limit))),
+            _ => panic!("ERR_POOL_NOT_REFUNDABLE"),
         }
     }
 
-    pub fn take_reward_from_pool(&mut self) -> Reward {
+    pub fn withdraw_nft_tokens(
+        &mut self,
+        token_ids: Option<Vec<TokenId>>,
+        limit: usize,
+    ) -> (AccountId, Vec<TokenId>) {
+        match self.kind {
+            PoolKind::NonFungibleToken(ref mut pool) => {
+                let token_ids = token_ids
+                    .unwrap_or_else(|| pool.available_tokens.iter().take(limit).cloned().collect());
+
+                require!(!token_ids.is_empty(), "ERR_NO_TOKENS_TO_WITHDRAW");
+                require!(
+                    token_ids.len() <= limit,
+                    format!(
+                        "A single withdrawal can't contain more than {} tokens",
+                        limit
+                    )
+                );
+
+                token_ids
+                    .iter()
+                    .for_each(|token_id| pool.remove_token(token_id));
+
+                (pool.contract_id.clone(), token_ids)
+            }
+            _ => panic!("ERR_NOT_NFT_POOL"),
+        }
+    }
+
+    pub fn take_reward_from_pool(&mut self, random_number: u128) -> Reward {
         match self.kind {
             PoolKind::Near(ref mut pool) => {
                 let amount = pool.decrease_available().unwrap();
 
                 Reward::Near { amount }
             }
+            PoolKind::NearRange(ref mut pool) => {
+                let amount = pool.take_random_amount(random_number);
+
+                Reward::Near { amount }
+            }
             PoolKind::NonFungibleToken(ref mut pool) => {
-                let token_id = pool.take_first_token().unwrap();
+                let token_id = pool.take_random_token(random_number as u64);
 
                 Reward::NonFungibleToken {
                     contract_id: pool.contract_id.clone(),
                     token_id,
                 }
             }
+            PoolKind::FungibleToken(ref mut pool) => {
+                let amount = pool.decrease_available().unwrap();
+
+                Reward::FungibleToken {
+                    contract_id: pool.contract_id.clone(),
+                    amount,
+                }
+            }
+            PoolKind::NonFungibleTokenMint(ref mut pool) => {
+                let token_id = pool.take_next_token_id();
+
+                Reward::NonFungibleTokenMint {
+                    contract_id: pool.contract_id.clone(),
+                    token_id,
+                    metadata: Box::new(pool.metadata.clone()),
+                    deposit: pool.deposit,
+                }
+            }
+            PoolKind::MultiToken(ref mut pool) => {
+                let token_id = pool.take_tokens();
+
+                Reward::MultiToken {
+                    contract_id: pool.contract_id.clone(),
+                    token_id,
+                    amount: pool.amount,
+                }
+            }
+            PoolKind::Bundle(_) => unreachable!(),
         }
     }
 
     pub fn put_reward_to_pool(&mut self, reward: Reward) {
         match reward {
-            Reward::Near { amount: _ } => match self.kind {
+            Reward::Near { amount } => match self.kind {
                 PoolKind::Near(ref mut pool) => pool.increase_available(),
+                PoolKind::NearRange(ref mut pool) => pool.put_amount_back(amount),
                 _ => unreachable!(),
             },
             Reward::NonFungibleToken {
@@ -92,6 +392,26 @@ impl Pool {
                 PoolKind::NonFungibleToken(ref mut pool) => pool.put_token_back(token_id),
                 _ => unreachable!(),
             },
+            Reward::FungibleToken {
+                contract_id: _,
+                amount: _,
+            } => match self.kind {
+                PoolKind::FungibleToken(ref mut pool) => pool.increase_available(),
+                _ => unreachable!(),
+            },
+            Reward::NonFungibleTokenMint { .. } => match self.kind {
+                PoolKind::NonFungibleTokenMint(ref mut pool) => pool.increase_available(),
+                _ => unreachable!(),
+            },
+            Reward::MultiToken {
+                contract_id: _,
+                token_id,
+                amount: _,
+            } => match self.kind {
+                PoolKind::MultiToken(ref mut pool) => pool.put_tokens_back(token_id),
+                _ => unreachable!(),
+            },
+            Reward::Bundle { rewards: _ } => unreachable!(),
         };
     }
 }
@@ -99,7 +419,12 @@ impl Pool {
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
 enum PoolKind {
     Near(NearPoolKind),
+    NearRange(NearRangePoolKind),
     NonFungibleToken(NonFungibleTokenPoolKind),
+    FungibleToken(FungibleTokenPoolKind),
+    NonFungibleTokenMint(Box<NonFungibleTokenMintPoolKind>),
+    MultiToken(MultiTokenPoolKind),
+    Bundle(BundlePoolKind),
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
@@ -129,6 +454,78 @@ impl NearPoolKind {
     pub fn increase_available(&mut self) {
         self.available += 1;
     }
+
+    pub fn top_up(&mut self, extra_capacity: Capacity) -> Balance {
+        require!(extra_capacity > 0, "ERR_ZERO_CAPACITY");
+
+        self.available += extra_capacity;
+        self.capacity += extra_capacity;
+
+        self.amount * (extra_capacity as u128)
+    }
+
+    pub fn withdraw(&mut self, count: Capacity) -> Balance {
+        require!(count > 0, "ERR_ZERO_COUNT");
+        require!(self.available >= count, "ERR_POOL_NOT_AVAILABLE");
+
+        self.available -= count;
+        self.capacity -= count;
+
+        self.amount * (count as u128)
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+pub struct NearRangePoolKind {
+    pub min_amount: Balance,
+    pub max_amount: Balance,
+    pub budget: Balance,
+    pub available_budget: Balance,
+}
+
+impl NearRangePoolKind {
+    pub fn new(min_amount: Balance, max_amount: Balance, budget: Balance) -> Self {
+        Self {
+            min_amount,
+            max_amount,
+            budget,
+            available_budget: budget,
+        }
+    }
+
+    // every claim takes at least the minimal amount, so it's the upper bound of remaining claims
+    pub fn availability(&self) -> Capacity {
+        (self.available_budget / self.min_amount) as Capacity
+    }
+
+    pub fn take_random_amount(&mut self, random_number: u128) -> Balance {
+        require!(
+            self.available_budget >= self.min_amount,
+            "ERR_POOL_NOT_AVAILABLE"
+        );
+
+        // never go beyond the budget that is left
+        let max_amount = self.max_amount.min(self.available_budget);
+
+        // bring to range [min_amount, max_amount]
+        let amount = self.min_amount + random_number % (max_amount - self.min_amount + 1);
+
+        self.available_budget -= amount;
+
+        amount
+    }
+
+    pub fn put_amount_back(&mut self, amount: Balance) {
+        self.available_budget += amount;
+    }
+
+    pub fn withdraw(&mut self, amount: Balance) {
+        require!(amount > 0, "ERR_ZERO_AMOUNT");
+        require!(self.available_budget >= amount, "ERR_POOL_NOT_AVAILABLE");
+
+        self.available_budget -= amount;
+        self.budget -= amount;
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
@@ -136,6 +533,8 @@ pub struct NonFungibleTokenPoolKind {
     pub contract_id: AccountId,
     pub tokens: BTreeSet<TokenId>,
     pub available_tokens: BTreeSet<TokenId>,
+    // tokens without a weight are drawn with the weight of 1
+    pub token_weights: BTreeMap<TokenId, Weight>,
 }
 
 impl NonFungibleTokenPoolKind {
@@ -144,25 +543,221 @@ impl NonFungibleTokenPoolKind {
             contract_id,
             tokens: BTreeSet::new(),
             available_tokens: BTreeSet::new(),
+            token_weights: BTreeMap::new(),
         }
     }
 
-    pub fn add_token(&mut self, token_id: TokenId) {
+    pub fn add_token(&mut self, token_id: TokenId, token_weight: Option<Weight>) {
         // should never panic
         require!(self.tokens.insert(token_id.clone()), format!("Token add problem {}", token_id.clone()));
         require!(self.available_tokens.insert(token_id.clone()), format!("Available tokens add problem {}", token_id.clone()));
+
+        match token_weight {
+            Option::Some(token_weight) => {
+                require!(token_weight > 0, "ERR_ZERO_WEIGHT");
+
+                self.token_weights.insert(token_id, token_weight);
+            }
+            Option::None => {
+                self.token_weights.remove(&token_id);
+            }
+        };
+    }
+
+    fn token_weight(&self, token_id: &TokenId) -> Weight {
+        self.token_weights.get(token_id).cloned().unwrap_or(1)
     }
 
-    pub fn take_first_token(&mut self) -> Option<TokenId> {
+    pub fn take_random_token(&mut self, random_number: u64) -> TokenId {
         require!(self.available_tokens.len() > 0, "ERR_POOL_NOT_AVAILABLE");
 
-        self.available_tokens.pop_first()
+        let total_weight: u128 = self
+            .available_tokens
+            .iter()
+            .map(|token_id| self.token_weight(token_id) as u128)
+            .sum();
+
+        // bring to range [0, total_weight - 1]
+        let random_in_range = (random_number as u128) % total_weight;
+
+        let mut last: u128 = 0;
+
+        let token_id = self
+            .available_tokens
+            .iter()
+            .find(|token_id| {
+                last += self.token_weight(token_id) as u128;
+
+                random_in_range < last
+            })
+            .cloned()
+            .expect("ERR_LOGIC");
+
+        self.available_tokens.remove(&token_id);
+
+        token_id
     }
 
     pub fn put_token_back(&mut self, token_id: TokenId) {
         // should never panic
         require!(self.available_tokens.insert(token_id));
     }
+
+    pub fn remove_token(&mut self, token_id: &TokenId) {
+        require!(
+            self.available_tokens.remove(token_id),
+            format!("Token {} isn't available in the pool", token_id)
+        );
+
+        // the weight is kept in case the token gets returned back
+        self.tokens.remove(token_id);
+    }
+
+    pub fn return_token(&mut self, token_id: TokenId) {
+        let token_weight = self.token_weights.get(&token_id).cloned();
+
+        self.add_token(token_id, token_weight);
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+pub struct FungibleTokenPoolKind {
+    pub contract_id: AccountId,
+    pub amount: Balance,
+    pub capacity: Capacity,
+    pub available: Capacity,
+}
+
+impl FungibleTokenPoolKind {
+    pub fn new(contract_id: AccountId, amount: Balance, capacity: Capacity) -> Self {
+        Self {
+            contract_id,
+            amount,
+            capacity,
+            available: capacity,
+        }
+    }
+
+    pub fn decrease_available(&mut self) -> Option<Balance> {
+        require!(self.available > 0, "ERR_POOL_NOT_AVAILABLE");
+
+        self.available -= 1;
+
+        Some(self.amount)
+    }
+
+    pub fn increase_available(&mut self) {
+        self.available += 1;
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+pub struct NonFungibleTokenMintPoolKind {
+    pub contract_id: AccountId,
+    pub token_id_prefix: String,
+    pub metadata: TokenMetadata,
+    // attached to each nft_mint call to cover the token storage
+    pub deposit: Balance,
+    pub capacity: Capacity,
+    pub available: Capacity,
+    pub next_token_index: u64,
+}
+
+impl NonFungibleTokenMintPoolKind {
+    pub fn new(
+        contract_id: AccountId,
+        token_id_prefix: String,
+        metadata: TokenMetadata,
+        deposit: Balance,
+        capacity: Capacity,
+    ) -> Self {
+        Self {
+            contract_id,
+            token_id_prefix,
+            metadata,
+            deposit,
+            capacity,
+            available: capacity,
+            next_token_index: 0,
+        }
+    }
+
+    pub fn take_next_token_id(&mut self) -> TokenId {
+        require!(self.available > 0, "ERR_POOL_NOT_AVAILABLE");
+
+        self.available -= 1;
+
+        // indexes are never reused, so a failed mint doesn't block the token id
+        let token_id = format!("{}:{}", self.token_id_prefix, self.next_token_index);
+
+        self.next_token_index += 1;
+
+        token_id
+    }
+
+    pub fn increase_available(&mut self) {
+        self.available += 1;
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+pub struct MultiTokenPoolKind {
+    pub contract_id: AccountId,
+    // amount of a single token paid for each claim
+    pub amount: Balance,
+    pub balances: BTreeMap<TokenId, Balance>,
+    pub total: Capacity,
+}
+
+impl MultiTokenPoolKind {
+    pub fn new(
+        contract_id: AccountId,
+        amount: Balance,
+        balances: BTreeMap<TokenId, Balance>,
+    ) -> Self {
+        let mut pool = Self {
+            contract_id,
+            amount,
+            balances,
+            total: 0,
+        };
+
+        pool.total = pool.availability();
+
+        pool
+    }
+
+    pub fn availability(&self) -> Capacity {
+        self.balances
+            .values()
+            .map(|balance| (balance / self.amount) as Capacity)
+            .sum()
+    }
+
+    pub fn take_tokens(&mut self) -> TokenId {
+        let amount = self.amount;
+
+        let (token_id, balance) = self
+            .balances
+            .iter_mut()
+            .find(|(_, balance)| **balance >= amount)
+            .expect("ERR_POOL_NOT_AVAILABLE");
+
+        *balance -= amount;
+
+        token_id.clone()
+    }
+
+    pub fn put_tokens_back(&mut self, token_id: TokenId) {
+        let balance = self.balances.entry(token_id).or_default();
+
+        *balance += self.amount;
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+pub struct BundlePoolKind {
+    pub pool_ids: Vec<PoolId>,
 }
 
 impl Into<JsonPoolRewards> for Pool {
@@ -173,11 +768,59 @@ impl Into<JsonPoolRewards> for Pool {
                 available: pool.available.clone(),
                 total: pool.capacity.clone(),
             },
+            PoolKind::NearRange(ref pool) => JsonPoolRewards::NearRange {
+                min_amount: pool.min_amount.to_owned().into(),
+                max_amount: pool.max_amount.to_owned().into(),
+                available: pool.available_budget.to_owned().into(),
+                total: pool.budget.to_owned().into(),
+            },
             PoolKind::NonFungibleToken(ref pool) => JsonPoolRewards::NonFungibleToken {
                 contract_id: pool.contract_id.clone(),
                 token_ids: pool.available_tokens.clone().into_iter().collect(),
+                token_weights: pool
+                    .token_weights
+                    .iter()
+                    .filter(|(token_id, _)| pool.available_tokens.contains(*token_id))
+                    .map(|(token_id, weight)| (token_id.clone(), U64(weight.to_owned())))
+                    .collect(),
                 total: pool.tokens.len() as u64,
             },
+            PoolKind::FungibleToken(ref pool) => JsonPoolRewards::FungibleToken {
+                contract_id: pool.contract_id.clone(),
+                amount: pool.amount.to_owned().into(),
+                available: pool.available,
+                total: pool.capacity,
+            },
+            PoolKind::NonFungibleTokenMint(ref pool) => JsonPoolRewards::NonFungibleTokenMint {
+                contract_id: pool.contract_id.clone(),
+                metadata: Box::new(pool.metadata.clone()),
+                available: pool.available,
+                total: pool.capacity,
+            },
+            PoolKind::MultiToken(ref pool) => JsonPoolRewards::MultiToken {
+                contract_id: pool.contract_id.clone(),
+                amount: pool.amount.to_owned().into(),
+                balances: pool
+                    .balances
+                    .iter()
+                    .map(|(token_id, balance)| (token_id.clone(), balance.to_owned().into()))
+                    .collect(),
+                available: pool.availability(),
+                total: pool.total,
+            },
+            // bundles are serialized by the quest, since their rewards live in other pools
+            PoolKind::Bundle(_) => unreachable!(),
+        }
+    }
+}
+
+impl From<Pool> for JsonPool {
+    fn from(pool: Pool) -> Self {
+        JsonPool {
+            pool_id: pool.id,
+            sponsor_id: pool.sponsor_id.clone(),
+            vesting: pool.vesting.clone(),
+            rewards: pool.into(),
         }
     }
 }
@@ -1,26 +1,46 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
-use near_sdk::collections::{ LookupMap, UnorderedSet };
+use near_sdk::collections::{ LookupMap, LookupSet, UnorderedSet };
 use near_sdk::json_types::{ U128, U64 };
 use near_sdk::{
     assert_one_yocto,
     env,
     require,
     AccountId,
+    Balance,
+    CurveType,
+    Gas,
     PanicOnDefault,
     Promise,
     PromiseOrValue,
+    PublicKey,
+    StorageUsage,
+    Timestamp,
 };
 use near_sdk::borsh::{ self, BorshDeserialize, BorshSerialize };
 
-use crate::contract::callbacks::create_withdraw_box_reward_promise_with_verification;
+use crate::contract::callbacks::{
+    create_claim_many_promise_with_verification,
+    create_withdraw_box_reward_promise_with_verification, transfer_reward_gas,
+};
 
-use crate::contract::types::{ BoxId, BoxStatus, PoolId, Probability };
+use crate::contract::types::{
+    BoxId, BoxMedia, BoxSale, BoxStatus, Capacity, PoolId, Probability, RarityTier,
+    VestingPosition, VestingSchedule,
+};
 
 use super::enums::StorageKey;
+use super::json::{JsonMintVoucher, JsonNftMint};
 use super::pools::Pool;
 use super::questbox::QuestBox;
-use super::types::{ BoxRarity, QuestId, TokenId };
+use super::types::{BasisPoints, BoxRarity, QuestId, TokenId};
+use super::vouchers::verify_voucher_signature;
+
+const MAX_NFT_WITHDRAWALS_PER_CALL: usize = 10;
+const MAX_BOXES_SWEPT_PER_CALL: u64 = 100;
+const MAX_BOXES_CLAIMED_PER_CALL: usize = 10;
+const MAX_BOXES_BOUGHT_PER_CALL: u64 = 50;
+const MAX_RARITY_TIERS: usize = 10;
 
 #[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
 pub struct Quest {
@@ -35,6 +55,37 @@ pub struct Quest {
     pub boxes: LookupMap<BoxId, QuestBox>,
     pub probability_by_rarity: LookupMap<BoxRarity, Probability>,
     pub users: UnorderedSet<AccountId>,
+    pub sponsors: UnorderedSet<AccountId>,
+    pub is_closed: bool,
+    // overrides the default platform fee
+    pub fee_bps: Option<BasisPoints>,
+    // rewards are locked in at the claim and withdrawn by users later
+    pub escrow_enabled: bool,
+    pub vesting_positions: LookupMap<AccountId, Vec<VestingPosition>>,
+    // box holders are able to pass boxes to other accounts
+    pub transfers_enabled: bool,
+    // boxes get the expiration of their rarity or the quest one at mint
+    pub expires_at: Option<Timestamp>,
+    pub expires_at_by_rarity: LookupMap<BoxRarity, Timestamp>,
+    pub deleted_boxes: BoxId,
+    pub sales_by_rarity: LookupMap<BoxRarity, BoxSale>,
+    // bought boxes are paid for, so the owner can't delete them
+    pub sold_boxes: LookupSet<BoxId>,
+    // proceeds of box sales, they don't back any pool
+    pub revenue: Balance,
+    // optional ceilings checked on every mint
+    pub max_boxes: Option<Capacity>,
+    pub max_boxes_by_rarity: LookupMap<BoxRarity, Capacity>,
+    pub max_boxes_per_account: Option<Capacity>,
+    pub boxes_by_rarity: LookupMap<BoxRarity, Capacity>,
+    pub minted_per_account: LookupMap<AccountId, Capacity>,
+    // ordered rarity tiers of the quest, empty means the default ones
+    pub tiers: Vec<RarityTier>,
+    // box art set by the owner, takes precedence over the tier media
+    pub media_by_rarity: LookupMap<BoxRarity, BoxMedia>,
+    // ed25519 key the owner signs mint vouchers with, None means vouchers aren't accepted
+    pub voucher_key: Option<PublicKey>,
+    pub used_voucher_nonces: LookupSet<u64>,
 }
 
 impl Quest {
@@ -54,6 +105,27 @@ impl Quest {
             boxes: LookupMap::new(StorageKey::Boxes { quest_hash }),
             probability_by_rarity: LookupMap::new(StorageKey::ProbabilityByRarity { quest_hash }),
             users: UnorderedSet::new(StorageKey::Users { quest_hash }),
+            sponsors: UnorderedSet::new(StorageKey::Sponsors { quest_hash }),
+            is_closed: false,
+            fee_bps: None,
+            escrow_enabled: false,
+            vesting_positions: LookupMap::new(StorageKey::VestingPositions { quest_hash }),
+            transfers_enabled: true,
+            expires_at: None,
+            expires_at_by_rarity: LookupMap::new(StorageKey::ExpiryByRarity { quest_hash }),
+            deleted_boxes: 0,
+            sales_by_rarity: LookupMap::new(StorageKey::SalesByRarity { quest_hash }),
+            sold_boxes: LookupSet::new(StorageKey::SoldBoxes { quest_hash }),
+            revenue: 0,
+            max_boxes: None,
+            max_boxes_by_rarity: LookupMap::new(StorageKey::MaxBoxesByRarity { quest_hash }),
+            max_boxes_per_account: None,
+            boxes_by_rarity: LookupMap::new(StorageKey::BoxesByRarity { quest_hash }),
+            minted_per_account: LookupMap::new(StorageKey::MintedPerAccount { quest_hash }),
+            tiers: Vec::new(),
+            media_by_rarity: LookupMap::new(StorageKey::MediaByRarity { quest_hash }),
+            voucher_key: None,
+            used_voucher_nonces: LookupSet::new(StorageKey::UsedVoucherNonces { quest_hash }),
         }
     }
 
@@ -61,30 +133,650 @@ impl Quest {
         require!(env::predecessor_account_id() == self.owner_id, "ERR_FORBIDDEN");
     }
 
-    pub fn add_near_reward(&mut self, rarity: BoxRarity, amount: U128, capacity: U64) {
-        self.assert_only_owner();
+    /// Returns the sponsor the rewards are attributed to, or None for the quest owner
+    fn assert_owner_or_sponsor(&self, account_id: &AccountId) -> Option<AccountId> {
+        if account_id == &self.owner_id {
+            return None;
+        }
+
+        require!(self.sponsors.contains(account_id), "ERR_FORBIDDEN");
+
+        Some(account_id.clone())
+    }
+
+    fn assert_not_closed(&self) {
+        require!(!self.is_closed, "ERR_QUEST_CLOSED");
+    }
+
+    pub fn assert_rarity(&self, rarity: &BoxRarity) {
+        require!(
+            self.tiers().iter().any(|tier| &tier.rarity == rarity),
+            format!("Rarity {} isn't a tier of the quest", rarity)
+        );
+    }
+
+    pub fn tiers(&self) -> Vec<RarityTier> {
+        if !self.tiers.is_empty() {
+            return self.tiers.clone();
+        }
+
+        Self::default_tiers()
+    }
+
+    pub fn default_tiers() -> Vec<RarityTier> {
+        BoxRarity::all()
+            .into_iter()
+            .map(|rarity| RarityTier {
+                rarity,
+                media: None,
+            })
+            .collect()
+    }
+
+    pub fn rarity_media(&self, rarity: &BoxRarity) -> BoxMedia {
+        if let Some(media) = self.media_by_rarity.get(rarity) {
+            return media;
+        }
+
+        let media = self
+            .tiers
+            .iter()
+            .find(|tier| &tier.rarity == rarity)
+            .and_then(|tier| tier.media())
+            .or_else(|| rarity.to_media_ipfs())
+            .unwrap_or_else(|| {
+                env::panic_str(&format!("Rarity {} isn't a tier of the quest", rarity))
+            });
+
+        BoxMedia::new(media)
+    }
+
+    pub fn assert_transfers_enabled(&self) {
+        require!(self.transfers_enabled, "ERR_TRANSFERS_DISABLED");
+    }
+
+    pub fn add_near_reward(
+        &mut self,
+        rarity: BoxRarity,
+        amount: U128,
+        capacity: U64,
+        weight: Option<U64>,
+    ) {
+        self.assert_not_closed();
+
+        let sponsor_id = self.assert_owner_or_sponsor(&env::predecessor_account_id());
 
         let pool_id = self.next_pool_id.clone();
 
         self.next_pool_id += 1;
 
-        let pool = Pool::create_near_pool(pool_id, rarity, amount.into(), capacity.into());
+        let mut pool = Pool::create_near_pool(pool_id, rarity, amount.into(), capacity.into());
+
+        pool.set_weight(weight.map(u64::from));
+        pool.sponsor_id = sponsor_id;
+
+        self.internal_insert_pool(&pool);
+    }
+
+    pub fn add_near_range_reward(
+        &mut self,
+        rarity: BoxRarity,
+        min_amount: U128,
+        max_amount: U128,
+        budget: U128,
+        weight: Option<U64>,
+    ) {
+        self.assert_not_closed();
+
+        let sponsor_id = self.assert_owner_or_sponsor(&env::predecessor_account_id());
+
+        require!(
+            min_amount.0 <= max_amount.0,
+            "ERR_MIN_AMOUNT_BIGGER_THAN_MAX_AMOUNT"
+        );
+        require!(
+            min_amount.0 <= budget.0,
+            "ERR_BUDGET_SMALLER_THAN_MIN_AMOUNT"
+        );
+
+        let pool_id = self.next_pool_id;
+
+        self.next_pool_id += 1;
+
+        let mut pool = Pool::create_near_range_pool(
+            pool_id,
+            rarity,
+            min_amount.into(),
+            max_amount.into(),
+            budget.into(),
+        );
+
+        pool.set_weight(weight.map(u64::from));
+        pool.sponsor_id = sponsor_id;
+
+        self.internal_insert_pool(&pool);
+    }
+
+    pub fn add_nft_mint_reward(
+        &mut self,
+        rarity: BoxRarity,
+        mint: JsonNftMint,
+        capacity: U64,
+        weight: Option<U64>,
+    ) {
+        self.assert_only_owner();
+        self.assert_not_closed();
+
+        require!(capacity.0 > 0, "ERR_ZERO_CAPACITY");
+
+        let pool_id = self.next_pool_id;
+
+        self.next_pool_id += 1;
+
+        let token_id_prefix = format!("{}:{}", self.id, pool_id);
+
+        let mut pool = Pool::create_nft_mint_pool(
+            pool_id,
+            rarity,
+            mint.contract_id,
+            token_id_prefix,
+            mint.metadata,
+            mint.deposit.into(),
+            capacity.into(),
+        );
+
+        pool.set_weight(weight.map(u64::from));
+
+        self.internal_insert_pool(&pool);
+    }
+
+    pub fn add_bundle_reward(
+        &mut self,
+        rarity: BoxRarity,
+        pool_ids: Vec<PoolId>,
+        weight: Option<U64>,
+    ) {
+        self.assert_only_owner();
+        self.assert_not_closed();
+
+        self.internal_add_bundle_pool(rarity, pool_ids, weight.map(u64::from));
+    }
+
+    pub fn set_pool_weight(&mut self, pool_id: PoolId, weight: Option<U64>) {
+        self.assert_only_owner();
+
+        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+        pool.set_weight(weight.map(u64::from));
+
+        self.pools.insert(&pool.id, &pool);
+    }
+
+    pub fn set_pool_vesting(&mut self, pool_id: PoolId, vesting: Option<VestingSchedule>) {
+        self.assert_only_owner();
+
+        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+        // bundled pools are hidden from their rarity
+        require!(
+            self.pool_ids_by_rarity
+                .get(&pool.rarity)
+                .unwrap_or_default()
+                .contains(&pool_id),
+            "Vesting can't be set on a bundled pool"
+        );
+
+        pool.set_vesting(vesting);
 
         self.pools.insert(&pool.id, &pool);
+    }
+
+    pub fn withdraw_vested(&mut self, account_id: &AccountId) -> Vec<(BoxId, Balance)> {
+        let now = env::block_timestamp();
+
+        let mut positions = self.vesting_positions.get(account_id).unwrap_or_default();
+
+        let withdrawn = positions
+            .iter_mut()
+            .filter_map(|position| {
+                let amount = position.withdrawable(now);
 
-        let mut pool_ids = self.pool_ids_by_rarity.get(&rarity).unwrap_or_default();
-        pool_ids.insert(pool_id.clone());
-        self.pool_ids_by_rarity.insert(&rarity, &pool_ids);
+                position.withdrawn += amount;
+
+                (amount > 0).then_some((position.box_id, amount))
+            })
+            .collect::<Vec<(BoxId, Balance)>>();
+
+        require!(!withdrawn.is_empty(), "ERR_NOTHING_VESTED");
+
+        self.vesting_positions.insert(account_id, &positions);
+
+        withdrawn
+    }
+
+    pub fn top_up_near_pool(&mut self, pool_id: PoolId, extra_capacity: Capacity) -> Balance {
+        self.assert_not_closed();
+
+        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+        // contributions of different accounts aren't mixed within a single pool
+        let funder_id = pool.sponsor_id.clone().unwrap_or(self.owner_id.clone());
+        require!(env::predecessor_account_id() == funder_id, "ERR_FORBIDDEN");
+
+        let reward_deposit = pool.top_up_near(extra_capacity);
+
+        self.pools.insert(&pool.id, &pool);
+
+        reward_deposit
+    }
+
+    pub fn withdraw_near_pool(&mut self, pool_id: PoolId, count: Capacity) -> Balance {
+        self.assert_only_owner();
+
+        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+        require!(pool.sponsor_id.is_none(), "ERR_SPONSORED_POOL");
+
+        let amount = pool.withdraw_near(count);
+
+        // nothing left to claim from the pool, so its storage can be released
+        if pool.is_drained() {
+            self.internal_remove_pool(&pool);
+        } else {
+            self.pools.insert(&pool.id, &pool);
+        }
+
+        amount
+    }
+
+    pub fn withdraw_near_range_pool(&mut self, pool_id: PoolId, amount: Balance) {
+        self.assert_only_owner();
+
+        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+        require!(pool.sponsor_id.is_none(), "ERR_SPONSORED_POOL");
+
+        pool.withdraw_near_range(amount);
+
+        // nothing left to claim from the pool, so its storage can be released
+        if pool.is_drained() {
+            self.internal_remove_pool(&pool);
+        } else {
+            self.pools.insert(&pool.id, &pool);
+        }
+    }
+
+    pub fn withdraw_nft_pool(
+        &mut self,
+        pool_id: PoolId,
+        token_ids: Option<Vec<TokenId>>,
+    ) -> (AccountId, Vec<TokenId>) {
+        self.assert_only_owner();
+
+        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+        require!(pool.sponsor_id.is_none(), "ERR_SPONSORED_POOL");
+
+        let withdrawn = pool.withdraw_nft_tokens(token_ids, MAX_NFT_WITHDRAWALS_PER_CALL);
+
+        self.pools.insert(&pool.id, &pool);
+
+        withdrawn
+    }
+
+    pub fn add_sponsor(&mut self, account_id: &AccountId) {
+        self.assert_only_owner();
+        self.assert_not_closed();
+
+        require!(
+            account_id != &self.owner_id,
+            "Quest owner can't be a sponsor"
+        );
+        require!(
+            self.sponsors.insert(account_id),
+            "ERR_SPONSOR_ALREADY_EXISTS"
+        );
+    }
+
+    pub fn remove_sponsor(&mut self, account_id: &AccountId) {
+        self.assert_only_owner();
+
+        // pools which were added before stay attributed to the sponsor
+        require!(self.sponsors.remove(account_id), "ERR_SPONSOR_NOT_FOUND");
+    }
+
+    pub fn close(&mut self) {
+        self.assert_only_owner();
+        self.assert_not_closed();
+
+        self.is_closed = true;
+    }
+
+    pub fn refund_sponsored_pool(
+        &mut self,
+        pool_id: PoolId,
+    ) -> (AccountId, Balance, Option<(AccountId, Vec<TokenId>)>) {
+        require!(self.is_closed, "ERR_QUEST_NOT_CLOSED");
+
+        let mut pool = self.pools.get(&pool_id).expect("ERR_POOL_NOT_FOUND");
+
+        let sponsor_id = pool.sponsor_id.clone().expect("ERR_NOT_SPONSORED_POOL");
+        require!(env::predecessor_account_id() == sponsor_id, "ERR_FORBIDDEN");
+
+        let (amount, nft_tokens) = pool.withdraw_unused(MAX_NFT_WITHDRAWALS_PER_CALL);
+
+        // nothing was ever claimed from the pool, so its storage can be released
+        if pool.is_drained() {
+            self.internal_remove_pool(&pool);
+        } else {
+            // claims in flight may still put their rewards back, so the pool is only hidden from the rarity
+            self.pools.insert(&pool.id, &pool);
+
+            let mut pool_ids = self
+                .pool_ids_by_rarity
+                .get(&pool.rarity)
+                .unwrap_or_default();
+            pool_ids.remove(&pool.id);
+            self.pool_ids_by_rarity.insert(&pool.rarity, &pool_ids);
+        }
+
+        (sponsor_id, amount, nft_tokens)
     }
 
     pub fn set_probability(&mut self, rarity: BoxRarity, probability: Probability) {
         probability.assert_valid();
 
         self.assert_only_owner();
+        self.assert_rarity(&rarity);
 
         self.probability_by_rarity.insert(&rarity, &probability);
     }
 
+    pub fn set_escrow_mode(&mut self, enabled: bool) {
+        self.assert_only_owner();
+
+        self.escrow_enabled = enabled;
+    }
+
+    /// Replaces the tiers of the quest, which is only possible before any pool or box is created
+    pub fn set_rarity_tiers(&mut self, tiers: Vec<(RarityTier, Option<Probability>)>) {
+        self.assert_only_owner();
+        self.assert_not_closed();
+
+        require!(
+            self.next_pool_id == 0 && self.next_box_id == 0,
+            "ERR_TIERS_LOCKED"
+        );
+        require!(!tiers.is_empty(), "ERR_NO_TIERS");
+        require!(tiers.len() <= MAX_RARITY_TIERS, "ERR_TOO_MANY_TIERS");
+
+        let rarities: HashSet<_> = tiers.iter().map(|(tier, _)| tier.rarity.clone()).collect();
+
+        require!(rarities.len() == tiers.len(), "ERR_DUPLICATED_TIERS");
+
+        for (tier, probability) in tiers.iter() {
+            tier.rarity.assert_valid();
+
+            require!(tier.media().is_some(), "ERR_TIER_MEDIA_REQUIRED");
+
+            if let Some(probability) = probability {
+                probability.assert_valid();
+            }
+        }
+
+        for tier in self.tiers() {
+            self.probability_by_rarity.remove(&tier.rarity);
+        }
+
+        self.tiers = tiers
+            .into_iter()
+            .map(|(tier, probability)| {
+                if let Some(probability) = probability {
+                    self.probability_by_rarity
+                        .insert(&tier.rarity, &probability);
+                }
+
+                tier
+            })
+            .collect();
+    }
+
+    /// Sets the box art of the rarity, or falls back to the tier media if none is given
+    pub fn set_box_media(&mut self, rarity: BoxRarity, media: Option<BoxMedia>) {
+        self.assert_only_owner();
+        self.assert_rarity(&rarity);
+
+        match media {
+            Some(media) => {
+                media.assert_valid();
+
+                self.media_by_rarity.insert(&rarity, &media);
+            }
+            None => {
+                self.media_by_rarity.remove(&rarity);
+            }
+        }
+    }
+
+    /// Sets the expiration for boxes of the rarity, or for all the others if no rarity is given
+    pub fn set_box_expiry(&mut self, rarity: Option<BoxRarity>, expires_at: Option<U64>) {
+        self.assert_only_owner();
+
+        if let Some(rarity) = &rarity {
+            self.assert_rarity(rarity);
+        }
+
+        match (rarity, expires_at) {
+            (Some(rarity), Some(expires_at)) => {
+                self.expires_at_by_rarity.insert(&rarity, &expires_at.0);
+            }
+            (Some(rarity), None) => {
+                self.expires_at_by_rarity.remove(&rarity);
+            }
+            (None, expires_at) => {
+                self.expires_at = expires_at.map(u64::from);
+            }
+        }
+    }
+
+    /// Marks expired boxes in the range as Expired and returns their ids
+    pub fn sweep_expired_boxes(&mut self, from_box_id: BoxId, limit: u64) -> Vec<BoxId> {
+        self.assert_only_owner();
+
+        let now = env::block_timestamp();
+
+        let to_box_id = self
+            .next_box_id
+            .min(from_box_id.saturating_add(limit.min(MAX_BOXES_SWEPT_PER_CALL) as BoxId));
+
+        (from_box_id..to_box_id)
+            .filter_map(|box_id| {
+                let mut box_data = self.boxes.get(&box_id)?;
+
+                if box_data.box_status != BoxStatus::NonClaimed || !box_data.is_expired(now) {
+                    return None;
+                }
+
+                box_data.box_status = BoxStatus::Expired;
+
+                self.boxes.insert(&box_id, &box_data);
+
+                Some(box_id)
+            })
+            .collect()
+    }
+
+    /// Caps boxes of the rarity, or all boxes of the quest if no rarity is given
+    pub fn set_max_boxes(&mut self, rarity: Option<BoxRarity>, max_boxes: Option<U64>) {
+        self.assert_only_owner();
+
+        let max_boxes = max_boxes.map(u64::from);
+
+        match rarity {
+            Some(rarity) => {
+                self.assert_rarity(&rarity);
+
+                let minted = self.boxes_by_rarity.get(&rarity).unwrap_or(0);
+
+                match max_boxes {
+                    Some(max_boxes) => {
+                        require!(max_boxes >= minted, "ERR_MAX_BOXES_LOWER_THAN_MINTED");
+
+                        self.max_boxes_by_rarity.insert(&rarity, &max_boxes);
+                    }
+                    None => {
+                        self.max_boxes_by_rarity.remove(&rarity);
+                    }
+                }
+            }
+            None => {
+                require!(
+                    max_boxes.is_none_or(|max_boxes| (max_boxes as u128) >= self.total_supply().0),
+                    "ERR_MAX_BOXES_LOWER_THAN_MINTED"
+                );
+
+                self.max_boxes = max_boxes;
+            }
+        }
+    }
+
+    pub fn set_max_boxes_per_account(&mut self, max_boxes: Option<U64>) {
+        self.assert_only_owner();
+
+        self.max_boxes_per_account = max_boxes.map(u64::from);
+    }
+
+    /// Puts boxes of the rarity on sale, or stops the sale if no price is given
+    pub fn set_box_sale(&mut self, rarity: BoxRarity, price: Option<U128>, supply: Option<U64>) {
+        self.assert_only_owner();
+        self.assert_rarity(&rarity);
+
+        let price = match price {
+            Some(price) => price.0,
+            None => {
+                self.sales_by_rarity.remove(&rarity);
+
+                return;
+            }
+        };
+
+        let sold = self
+            .sales_by_rarity
+            .get(&rarity)
+            .map(|sale| sale.sold)
+            .unwrap_or(0);
+
+        let supply = supply.map(u64::from);
+
+        require!(
+            supply.is_none_or(|supply| supply >= sold),
+            "ERR_SUPPLY_LOWER_THAN_SOLD"
+        );
+
+        self.sales_by_rarity.insert(
+            &rarity,
+            &(BoxSale {
+                price,
+                supply,
+                sold,
+            }),
+        );
+    }
+
+    /// Mints the bought boxes to the buyer and returns them along with the price to be paid
+    pub fn buy_boxes(&mut self, rarity: BoxRarity, count: u64) -> (Vec<QuestBox>, Balance) {
+        self.assert_not_closed();
+
+        require!(count > 0, "ERR_ZERO_COUNT");
+        require!(count <= MAX_BOXES_BOUGHT_PER_CALL, "ERR_TOO_MANY_BOXES");
+
+        let mut sale = self
+            .sales_by_rarity
+            .get(&rarity)
+            .expect("ERR_BOX_NOT_FOR_SALE");
+
+        require!(
+            sale.available().is_none_or(|available| available >= count),
+            "ERR_NOT_ENOUGH_BOXES_FOR_SALE"
+        );
+
+        sale.sold += count;
+
+        self.sales_by_rarity.insert(&rarity, &sale);
+
+        let cost = sale.price * (count as u128);
+
+        let buyer_id = env::predecessor_account_id();
+
+        let boxes: Vec<QuestBox> = (0..count)
+            .map(|_| self.internal_mint(buyer_id.clone(), rarity.clone()))
+            .collect();
+
+        boxes.iter().for_each(|questbox| {
+            self.sold_boxes.insert(&questbox.box_id);
+        });
+
+        (boxes, cost)
+    }
+
+    pub fn set_voucher_key(&mut self, public_key: Option<PublicKey>) {
+        self.assert_only_owner();
+
+        require!(
+            public_key
+                .as_ref()
+                .is_none_or(|public_key| public_key.curve_type() == CurveType::ED25519),
+            "ERR_VOUCHER_KEY_NOT_ED25519"
+        );
+
+        self.voucher_key = public_key;
+    }
+
+    /// Mints the box of a voucher signed with the voucher key of the quest to its receiver
+    pub fn redeem_voucher(&mut self, voucher: &JsonMintVoucher, signature: &[u8]) -> QuestBox {
+        self.assert_not_closed();
+
+        require!(voucher.quest_id == self.id, "ERR_VOUCHER_OF_ANOTHER_QUEST");
+        require!(
+            env::block_timestamp() < voucher.expires_at.0,
+            "ERR_VOUCHER_EXPIRED"
+        );
+
+        let voucher_key = self
+            .voucher_key
+            .as_ref()
+            .expect("ERR_VOUCHERS_NOT_ACCEPTED");
+
+        require!(
+            verify_voucher_signature(voucher_key, &voucher.to_message(), signature),
+            "ERR_INVALID_VOUCHER_SIGNATURE"
+        );
+        require!(
+            self.used_voucher_nonces.insert(&voucher.nonce.0),
+            "ERR_VOUCHER_ALREADY_REDEEMED"
+        );
+
+        self.internal_mint(voucher.receiver_id.clone(), voucher.rarity.clone())
+    }
+
+    pub fn withdraw_revenue(&mut self) -> Balance {
+        self.assert_only_owner();
+
+        require!(self.revenue > 0, "ERR_NO_REVENUE_TO_WITHDRAW");
+
+        let amount = self.revenue;
+
+        self.revenue = 0;
+
+        amount
+    }
+
+    pub fn set_transfers_enabled(&mut self, enabled: bool) {
+        self.assert_only_owner();
+
+        self.transfers_enabled = enabled;
+    }
+
     pub fn set_owner(&mut self, new_owner_id: &AccountId) {
         // only owner can set another owner
         self.assert_only_owner();
@@ -94,42 +786,144 @@ impl Quest {
 
     pub fn mint(&mut self, box_owner_id: AccountId, rarity: BoxRarity) -> QuestBox {
         self.assert_only_owner();
+        self.assert_not_closed();
 
         let box_data = self.internal_mint(box_owner_id.clone(), rarity.clone());
 
         return box_data;
     }
 
-    pub fn delete_boxes(&mut self, ids: &Vec<BoxId>) {
+    pub fn delete_box(&mut self, box_id: BoxId) -> QuestBox {
         self.assert_only_owner();
 
-        ids.iter().for_each(|box_id| {
-            assert!(self.boxes.get(&box_id).is_some(), "Box {} doesn't exist", &box_id);
+        assert!(
+            self.boxes.get(&box_id).is_some(),
+            "Box {} doesn't exist",
+            &box_id
+        );
 
-            let box_data = self.boxes.remove(box_id).unwrap();
+        require!(
+            !self.sold_boxes.contains(&box_id),
+            format!("Box {} was sold", box_id)
+        );
 
-            require!(
-                box_data.box_status == BoxStatus::NonClaimed,
-                format!("Box {} already claimed", box_data.box_id)
-            );
-        });
+        let box_data = self.boxes.remove(&box_id).unwrap();
+
+        // expired boxes can't be claimed anymore, so they are deleted as well
+        require!(
+            box_data.box_status == BoxStatus::NonClaimed
+                || box_data.box_status == BoxStatus::Expired,
+            format!("Box {} already claimed", box_data.box_id)
+        );
+
+        self.deleted_boxes += 1;
+
+        let minted = self.boxes_by_rarity.get(&box_data.box_rarity).unwrap_or(0);
+
+        self.boxes_by_rarity
+            .insert(&box_data.box_rarity, &minted.saturating_sub(1));
+
+        // keyed boxes held by the contract aren't counted per account
+        if box_data.owner_id != env::current_account_id() {
+            if let Some(minted) = self.minted_per_account.get(&box_data.owner_id) {
+                self.minted_per_account
+                    .insert(&box_data.owner_id, &minted.saturating_sub(1));
+            }
+        }
+
+        box_data
     }
 
-    pub fn claim(&mut self, box_id: BoxId) -> Promise {
-        assert_one_yocto();
+    /// Returns the reward promise along with the storage of the vesting position, the claimer pays for it
+    pub fn claim(&mut self, box_id: BoxId) -> (Promise, StorageUsage) {
+        self.claim_for(box_id, &env::predecessor_account_id())
+    }
 
+    // boxes minted before the quest was closed stay claimable, they get what's left in the pools
+    pub fn claim_for(&mut self, box_id: BoxId, account_id: &AccountId) -> (Promise, StorageUsage) {
         require!(self.boxes.contains_key(&box_id), "ERR_BOX_NOT_FOUND");
 
+        let (pool_id, vesting_storage) = self.internal_claim(box_id, account_id);
+
+        let promise = create_withdraw_box_reward_promise_with_verification(
+            account_id,
+            self.id,
+            &box_id,
+            &pool_id,
+            self.escrow_enabled,
+        );
+
+        (promise, vesting_storage)
+    }
+
+    pub fn claim_many(&mut self, box_ids: &[BoxId]) -> (Promise, StorageUsage) {
+        require!(!box_ids.is_empty(), "ERR_NO_BOXES_TO_CLAIM");
+        require!(
+            box_ids.len() <= MAX_BOXES_CLAIMED_PER_CALL,
+            "ERR_TOO_MANY_BOXES"
+        );
+
+        let unique_box_ids: HashSet<BoxId> = box_ids.iter().cloned().collect();
+        require!(
+            unique_box_ids.len() == box_ids.len(),
+            "ERR_DUPLICATED_BOXES"
+        );
+
         let account_id = env::predecessor_account_id();
 
-        let pool_id = self.internal_claim(box_id);
+        let mut vesting_storage = 0;
+
+        let claims = box_ids
+            .iter()
+            .map(|box_id| {
+                require!(self.boxes.contains_key(box_id), "ERR_BOX_NOT_FOUND");
+
+                let (pool_id, storage_used) = self.internal_claim(*box_id, &account_id);
+
+                vesting_storage += storage_used;
+
+                (*box_id, pool_id)
+            })
+            .collect::<Vec<(BoxId, PoolId)>>();
 
-        create_withdraw_box_reward_promise_with_verification(
+        let transfer_gas = box_ids
+            .iter()
+            .filter_map(|box_id| match self.boxes.get(box_id)?.box_status {
+                BoxStatus::Claimed {
+                    reward: Some(reward),
+                } => Some(transfer_reward_gas(&reward)),
+                _ => None,
+            })
+            .fold(Gas(0), |total, gas| total + gas);
+
+        let promise = create_claim_many_promise_with_verification(
             &account_id,
             self.id,
-            &box_id,
-            &pool_id
-        )
+            &claims,
+            transfer_gas,
+            self.escrow_enabled,
+        );
+
+        (promise, vesting_storage)
+    }
+
+    pub fn transfer_box(
+        &mut self,
+        box_id: BoxId,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> QuestBox {
+        let mut box_data = self.boxes.get(&box_id).expect("ERR_BOX_NOT_FOUND");
+
+        require!(&box_data.owner_id == sender_id, "ERR_FORBIDDEN");
+        require!(sender_id != receiver_id, "ERR_SAME_RECEIVER");
+
+        box_data.owner_id = receiver_id.clone();
+
+        self.boxes.insert(&box_id, &box_data);
+        self.users.insert(receiver_id);
+
+        box_data
     }
 
     pub fn nft_on_transfer(
@@ -137,25 +931,192 @@ impl Quest {
         #[allow(unused_variables)] sender_id: AccountId,
         previous_owner_id: AccountId,
         token_id: TokenId,
-        box_rarity: BoxRarity
+        box_rarity: BoxRarity,
+        token_weight: Option<U64>,
+        weight: Option<U64>,
     ) -> PromiseOrValue<bool> {
         let nft_account_id = env::predecessor_account_id();
 
+        self.assert_not_closed();
+
         //there is no point in sending nft to itself
-        require!(self.owner_id == previous_owner_id, "ERR_FORBIDDEN");
+        let sponsor_id = self.assert_owner_or_sponsor(&previous_owner_id);
 
-        self.internal_add_nft_pool(box_rarity, nft_account_id, token_id);
+        self.internal_add_nft_pool(
+            box_rarity,
+            nft_account_id,
+            token_id,
+            token_weight.map(u64::from),
+            weight.map(u64::from),
+            sponsor_id,
+        );
 
         // stands for OK response
         PromiseOrValue::Value(false)
     }
 
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        box_rarity: BoxRarity,
+        reward_amount: U128,
+        capacity: U64,
+        weight: Option<U64>,
+    ) -> PromiseOrValue<U128> {
+        let ft_account_id = env::predecessor_account_id();
+
+        self.assert_not_closed();
+
+        require!(self.owner_id == sender_id, "ERR_FORBIDDEN");
+
+        require!(reward_amount.0 > 0, "ERR_ZERO_REWARD_AMOUNT");
+        require!(capacity.0 > 0, "ERR_ZERO_CAPACITY");
+
+        let reward_deposit = reward_amount.0 * (capacity.0 as u128);
+
+        require!(
+            amount.0 >= reward_deposit,
+            format!(
+                "Transferred amount must be at least {} tokens",
+                reward_deposit
+            )
+        );
+
+        let pool_id = self.next_pool_id;
+
+        self.next_pool_id += 1;
+
+        let mut pool = Pool::create_ft_pool(
+            pool_id,
+            box_rarity,
+            ft_account_id,
+            reward_amount.into(),
+            capacity.into(),
+        );
+
+        pool.set_weight(weight.map(u64::from));
+
+        self.internal_insert_pool(&pool);
+
+        // return back the tokens which weren't used for rewards
+        PromiseOrValue::Value(U128(amount.0 - reward_deposit))
+    }
+
+    pub fn mt_on_transfer(
+        &mut self,
+        previous_owner_ids: Vec<AccountId>,
+        token_ids: Vec<TokenId>,
+        amounts: Vec<U128>,
+        box_rarity: BoxRarity,
+        reward_amount: U128,
+        weight: Option<U64>,
+    ) -> PromiseOrValue<Vec<U128>> {
+        let mt_account_id = env::predecessor_account_id();
+
+        self.assert_not_closed();
+
+        require!(
+            previous_owner_ids.len() == token_ids.len() && token_ids.len() == amounts.len(),
+            "ERR_INVALID_ARGUMENTS"
+        );
+
+        require!(
+            previous_owner_ids
+                .iter()
+                .all(|previous_owner_id| previous_owner_id == &self.owner_id),
+            "ERR_FORBIDDEN"
+        );
+
+        require!(reward_amount.0 > 0, "ERR_ZERO_REWARD_AMOUNT");
+
+        let mut balances: BTreeMap<TokenId, Balance> = BTreeMap::new();
+
+        // only the amount that makes up whole rewards is kept
+        let unused_amounts = token_ids
+            .iter()
+            .zip(amounts.iter())
+            .map(|(token_id, amount)| {
+                let used_amount = amount.0 - amount.0 % reward_amount.0;
+
+                if used_amount > 0 {
+                    *balances.entry(token_id.clone()).or_default() += used_amount;
+                }
+
+                U128(amount.0 - used_amount)
+            })
+            .collect::<Vec<U128>>();
+
+        require!(!balances.is_empty(), "ERR_NOT_ENOUGH_TOKENS");
+
+        let pool_id = self.next_pool_id;
+
+        self.next_pool_id += 1;
+
+        let mut pool = Pool::create_mt_pool(
+            pool_id,
+            box_rarity,
+            mt_account_id,
+            reward_amount.into(),
+            balances,
+        );
+
+        pool.set_weight(weight.map(u64::from));
+
+        self.internal_insert_pool(&pool);
+
+        PromiseOrValue::Value(unused_amounts)
+    }
+
     fn internal_mint(&mut self, box_owner_id: AccountId, rarity: BoxRarity) -> QuestBox {
+        self.assert_rarity(&rarity);
+
+        require!(
+            self.max_boxes
+                .is_none_or(|max_boxes| (max_boxes as u128) > self.total_supply().0),
+            "ERR_MAX_BOXES_REACHED"
+        );
+
+        let minted_by_rarity = self.boxes_by_rarity.get(&rarity).unwrap_or(0);
+
+        require!(
+            self.max_boxes_by_rarity
+                .get(&rarity)
+                .is_none_or(|max_boxes| max_boxes > minted_by_rarity),
+            "ERR_MAX_BOXES_BY_RARITY_REACHED"
+        );
+
+        self.boxes_by_rarity
+            .insert(&rarity, &(minted_by_rarity + 1));
+
+        // keyed boxes are held by the contract until claimed, so they aren't limited per account
+        if box_owner_id != env::current_account_id() {
+            let minted_per_account = self.minted_per_account.get(&box_owner_id).unwrap_or(0);
+
+            require!(
+                self.max_boxes_per_account
+                    .is_none_or(|max_boxes| max_boxes > minted_per_account),
+                "ERR_MAX_BOXES_PER_ACCOUNT_REACHED"
+            );
+
+            self.minted_per_account
+                .insert(&box_owner_id, &(minted_per_account + 1));
+        }
+
         let box_id = self.next_box_id.clone();
 
         self.next_box_id += 1;
 
-        let box_data = QuestBox::new(self.id, box_id, rarity, box_owner_id);
+        let expires_at = self.expires_at_by_rarity.get(&rarity).or(self.expires_at);
+
+        let box_data = QuestBox::new(
+            self.id,
+            box_id,
+            rarity,
+            box_owner_id,
+            expires_at,
+            env::predecessor_account_id(),
+        );
 
         self.boxes.insert(&box_data.box_id, &box_data);
 
@@ -1,46 +1,77 @@
 
+use std::io;
+
 use near_sdk::borsh::{ self, BorshDeserialize, BorshSerialize };
 use near_sdk::serde::{ Deserialize, Serialize };
-use near_sdk::AccountId;
+use near_sdk::{AccountId, Timestamp};
 
-use super::json::JsonBox;
 use super::types::{BoxId, BoxRarity, QuestId, BoxStatus};
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
-#[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
+#[derive(BorshSerialize, Debug, Clone)]
 pub struct QuestBox {
     pub box_id: BoxId,
     pub box_rarity: BoxRarity,
     pub box_status: BoxStatus,
     pub quest_id: QuestId,
     pub owner_id: AccountId,
+    pub expires_at: Option<Timestamp>,
+    // paid for the box storage, so gets it back on deletion, None means the quest owner
+    pub minter_id: Option<AccountId>,
+}
+
+// boxes of the first release end with the owner, they never expire and were minted by the quest owner
+impl BorshDeserialize for QuestBox {
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        let box_id: BoxId = BorshDeserialize::deserialize(buf)?;
+        let box_rarity: BoxRarity = BorshDeserialize::deserialize(buf)?;
+        let box_status: BoxStatus = BorshDeserialize::deserialize(buf)?;
+        let quest_id: QuestId = BorshDeserialize::deserialize(buf)?;
+        let owner_id: AccountId = BorshDeserialize::deserialize(buf)?;
+
+        let (expires_at, minter_id) = if buf.is_empty() {
+            (None, None)
+        } else {
+            (
+                BorshDeserialize::deserialize(buf)?,
+                BorshDeserialize::deserialize(buf)?,
+            )
+        };
+
+        Ok(Self {
+            box_id,
+            box_rarity,
+            box_status,
+            quest_id,
+            owner_id,
+            expires_at,
+            minter_id,
+        })
+    }
 }
 
 impl QuestBox {
-    pub fn new(quest_id: QuestId, box_id: BoxId, rarity: BoxRarity, box_owner_id: AccountId) -> Self {
+    pub fn new(
+        quest_id: QuestId,
+        box_id: BoxId,
+        rarity: BoxRarity,
+        box_owner_id: AccountId,
+        expires_at: Option<Timestamp>,
+        minter_id: AccountId,
+    ) -> Self {
         Self {
             quest_id,
             box_id,
             box_rarity: rarity,
             box_status: BoxStatus::NonClaimed,
-            owner_id: box_owner_id
+            owner_id: box_owner_id,
+            expires_at,
+            minter_id: Some(minter_id),
         }
     }
 
-    pub fn ipfs(&self) -> String {
-        self.box_rarity.to_media_ipfs()
-    }
-}
-
-impl From<QuestBox> for JsonBox {
-    fn from(value: QuestBox) -> Self {
-        Self {
-            quest_id: value.quest_id,
-            box_id: value.box_id,
-            ipfs: value.ipfs(),
-            box_rarity: value.box_rarity,
-            box_status: value.box_status.into(),
-        }
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
     }
 }
@@ -0,0 +1,143 @@
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{assert_one_yocto, env, near_bindgen, Promise};
+
+use crate::{Contract, ContractExt};
+
+use super::callbacks::create_withdraw_revenue_promise;
+use super::events::{Event, RevenueWithdrawalData};
+use super::json::JsonBoxSale;
+use super::types::{BoxId, BoxRarity, QuestId};
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn set_box_sale(
+        &mut self,
+        quest_id: QuestId,
+        rarity: BoxRarity,
+        price: Option<U128>,
+        supply: Option<U64>,
+    ) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        quest.set_box_sale(rarity, price, supply);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    /// Mints the boxes to the caller, the deposit has to cover their price and storage
+    #[payable]
+    pub fn buy_box(&mut self, quest_id: QuestId, rarity: BoxRarity, count: u64) -> Vec<BoxId> {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        let (questboxes, cost) = quest.buy_boxes(rarity, count);
+
+        // the platform fee is taken out of the price
+        let fee = self.internal_take_fee(&quest, cost);
+
+        quest.revenue += cost - fee;
+
+        self.quests.insert(&quest.id, &quest);
+
+        questboxes.iter().for_each(|questbox| {
+            self.mint_boxes_per_owner(questbox);
+        });
+
+        let storage_used_after = env::storage_usage();
+
+        let deposit =
+            env::storage_byte_cost() * ((storage_used_after - storage_used_before) as u128) + cost;
+
+        assert!(
+            env::attached_deposit() >= deposit,
+            "Deposited amount must be bigger than {} yocto",
+            deposit
+        );
+
+        let refund = env::attached_deposit() - deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+
+        questboxes
+            .into_iter()
+            .map(|questbox| questbox.box_id)
+            .collect()
+    }
+
+    #[payable]
+    pub fn withdraw_revenue(&mut self, quest_id: QuestId) -> Promise {
+        assert_one_yocto();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let amount = quest.withdraw_revenue();
+
+        self.quests.insert(&quest_id, &quest);
+
+        Event::RevenueWithdrawal(vec![RevenueWithdrawalData {
+            quest_id,
+            receiver_id: quest.owner_id.clone(),
+            amount: U128(amount),
+        }])
+        .emit();
+
+        create_withdraw_revenue_promise(&quest.owner_id, quest_id, amount)
+    }
+
+    pub fn get_box_sales(&self, quest_id: QuestId) -> Vec<JsonBoxSale> {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest
+            .tiers()
+            .into_iter()
+            .filter_map(|tier| {
+                let rarity = tier.rarity;
+                let sale = quest.sales_by_rarity.get(&rarity)?;
+
+                Some(JsonBoxSale {
+                    rarity,
+                    price: U128(sale.price),
+                    supply: sale.supply.map(U64),
+                    sold: U64(sale.sold),
+                })
+            })
+            .collect()
+    }
+
+    pub fn get_revenue(&self, quest_id: QuestId) -> U128 {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        U128(quest.revenue)
+    }
+}
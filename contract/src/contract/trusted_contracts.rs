@@ -26,4 +26,21 @@ pub fn get_trusted_nft_contracts() -> Vec<AccountId> {
             "nft.thedons.near".parse().unwrap(),
         ]
     };
-}
\ No newline at end of file
+}
+pub fn get_trusted_ft_contracts() -> Vec<AccountId> {
+    let network = Network::from(env::predecessor_account_id());
+
+    match network {
+        Network::Testnet => vec![
+            "wrap.testnet".parse().unwrap(),
+            "usdc.fakes.testnet".parse().unwrap(),
+        ],
+        Network::Mainnet => vec![
+            "wrap.near".parse().unwrap(),
+            "17208628f84f5d6ad33f0da3bbbeb27ffcb398eac501a31bd6ad2011e36133a1"
+                .parse()
+                .unwrap(),
+            "usdt.tether-token.near".parse().unwrap(),
+        ],
+    }
+}
@@ -1,12 +1,17 @@
 
 use std::fmt::{Display, Formatter, Result};
+use std::io::{self, Write};
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{require, AccountId, Balance};
+use near_sdk::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use near_sdk::json_types::{Base64VecU8, U64};
+use near_sdk::{require, AccountId, Balance, Timestamp};
 
 // modules
 pub mod questbox_data;
+pub mod token_metadata;
+
+use token_metadata::TokenMetadata;
 
 pub type TokenId = String;
 pub type BoxId = u128;
@@ -14,15 +19,18 @@ pub type QuestTitle = String;
 pub type QuestId = u64;
 pub type PoolId = u32;
 pub type Capacity = u64;
+pub type Weight = u64;
+pub type BasisPoints = u16;
 
-#[derive(
-    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, Copy,
-)]
-#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+const MAX_RARITY_NAME_LENGTH: usize = 32;
+
+// quests without own tiers use rare, epic and legendary, the variant order keeps the stored layout
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BoxRarity {
     Rare,
     Epic,
     Legendary,
+    Custom(String),
 }
 
 impl Display for BoxRarity {
@@ -31,15 +39,40 @@ impl Display for BoxRarity {
             BoxRarity::Rare => write!(f, "rare"),
             BoxRarity::Epic => write!(f, "epic"),
             BoxRarity::Legendary => write!(f, "legendary"),
+            BoxRarity::Custom(name) => write!(f, "{}", name),
         }
     }
 }
 
+// every tier is a plain name in JSON, so "rare" keeps working as before
+impl Serialize for BoxRarity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for BoxRarity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let name = <String as Deserialize>::deserialize(deserializer)?;
+
+        Ok(BoxRarity::new(&name))
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+pub struct RarityTier {
+    pub rarity: BoxRarity,
+    // IPFS CID of the box image, the default tiers fall back to the bundled ones
+    pub media: Option<String>,
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
 pub enum BoxStatus {
     Claimed { reward: Option<Reward> },
     NonClaimed,
+    // the box wasn't claimed before its expiration and was swept by the quest owner
+    Expired,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -52,25 +85,296 @@ pub enum Reward {
         contract_id: AccountId,
         token_id: TokenId,
     },
+    FungibleToken {
+        contract_id: AccountId,
+        amount: Balance,
+    },
+    NonFungibleTokenMint {
+        contract_id: AccountId,
+        token_id: TokenId,
+        metadata: Box<TokenMetadata>,
+        deposit: Balance,
+    },
+    MultiToken {
+        contract_id: AccountId,
+        token_id: TokenId,
+        amount: Balance,
+    },
+    Bundle {
+        rewards: Vec<BundledReward>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub struct BundledReward {
+    pub pool_id: PoolId,
+    pub reward: Reward,
+}
+
+// derived implementations can't resolve the recursion between Reward and BundledReward
+impl BorshSerialize for BundledReward {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        BorshSerialize::serialize(&self.pool_id, writer)?;
+        BorshSerialize::serialize(&self.reward, writer)
+    }
+}
+
+impl BorshDeserialize for BundledReward {
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            pool_id: BorshDeserialize::deserialize(buf)?,
+            reward: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+// durations are in nanoseconds, the whole amount is released at the cliff when it equals the duration
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingSchedule {
+    pub cliff: U64,
+    pub duration: U64,
+}
+
+impl VestingSchedule {
+    pub fn assert_valid(&self) {
+        require!(self.duration.0 > 0, "ERR_ZERO_VESTING_DURATION");
+        require!(
+            self.cliff.0 <= self.duration.0,
+            "ERR_CLIFF_LONGER_THAN_DURATION"
+        );
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+pub struct VestingPosition {
+    pub box_id: BoxId,
+    pub amount: Balance,
+    pub withdrawn: Balance,
+    pub start: Timestamp,
+    pub schedule: VestingSchedule,
+}
+
+impl VestingPosition {
+    pub fn unlocked(&self, now: Timestamp) -> Balance {
+        let elapsed = now.saturating_sub(self.start);
+
+        if elapsed < self.schedule.cliff.0 {
+            return 0;
+        }
+
+        if elapsed >= self.schedule.duration.0 {
+            return self.amount;
+        }
+
+        let duration = self.schedule.duration.0 as u128;
+        let elapsed = elapsed as u128;
+
+        // split to not overflow with yocto amounts multiplied by nanoseconds
+        (self.amount / duration) * elapsed + (self.amount % duration) * elapsed / duration
+    }
+
+    pub fn withdrawable(&self, now: Timestamp) -> Balance {
+        self.unlocked(now) - self.withdrawn
+    }
+
+    pub fn is_withdrawn(&self) -> bool {
+        self.withdrawn == self.amount
+    }
+}
+
+// boxes of the rarity are sold to anyone for the price until the supply runs out
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+pub struct BoxSale {
+    pub price: Balance,
+    pub supply: Option<Capacity>,
+    pub sold: Capacity,
+}
+
+impl BoxSale {
+    pub fn available(&self) -> Option<Capacity> {
+        self.supply.map(|supply| supply - self.sold)
+    }
+}
+
+// reward which is locked in for the account, but hasn't been transferred yet
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingReward {
+    pub quest_id: QuestId,
+    pub box_id: BoxId,
+    pub reward: Reward,
+}
+
+impl Reward {
+    // each part of the reward is transferred with a separate promise
+    pub fn parts_count(&self) -> usize {
+        match self {
+            Reward::Bundle { rewards } => rewards.len(),
+            _ => 1,
+        }
+    }
+
+    // keeps only the parts with provided indexes, the single reward is kept as it is
+    pub fn parts(self, indexes: &[usize]) -> Reward {
+        match self {
+            Reward::Bundle { rewards } => Reward::Bundle {
+                rewards: rewards
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, _)| indexes.contains(index))
+                    .map(|(_, bundled_reward)| bundled_reward)
+                    .collect(),
+            },
+            reward => reward,
+        }
+    }
+
+    pub fn to_parts(&self) -> Vec<Reward> {
+        match self {
+            Reward::Bundle { rewards } => rewards
+                .iter()
+                .map(|bundled_reward| bundled_reward.reward.clone())
+                .collect(),
+            reward => vec![reward.clone()],
+        }
+    }
+
+    // adds up rewards of the same asset, so they can be transferred at once
+    pub fn merge(&mut self, other: &Reward) -> bool {
+        match (self, other) {
+            (
+                Reward::Near { amount },
+                Reward::Near {
+                    amount: other_amount,
+                },
+            ) => {
+                *amount += other_amount;
+
+                true
+            }
+            (
+                Reward::FungibleToken {
+                    contract_id,
+                    amount,
+                },
+                Reward::FungibleToken {
+                    contract_id: other_contract_id,
+                    amount: other_amount,
+                },
+            ) if contract_id == other_contract_id => {
+                *amount += other_amount;
+
+                true
+            }
+            (
+                Reward::MultiToken {
+                    contract_id,
+                    token_id,
+                    amount,
+                },
+                Reward::MultiToken {
+                    contract_id: other_contract_id,
+                    token_id: other_token_id,
+                    amount: other_amount,
+                },
+            ) if contract_id == other_contract_id && token_id == other_token_id => {
+                *amount += other_amount;
+
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 impl BoxRarity {
-    pub fn to_media_ipfs(&self) -> String {
+    pub fn new(name: &str) -> Self {
+        match name {
+            "rare" => BoxRarity::Rare,
+            "epic" => BoxRarity::Epic,
+            "legendary" => BoxRarity::Legendary,
+            _ => BoxRarity::Custom(name.to_string()),
+        }
+    }
+
+    pub fn all() -> Vec<BoxRarity> {
+        vec![BoxRarity::Rare, BoxRarity::Epic, BoxRarity::Legendary]
+    }
+
+    pub fn assert_valid(&self) {
+        if let BoxRarity::Custom(name) = self {
+            require!(
+                !name.is_empty()
+                    && name.len() <= MAX_RARITY_NAME_LENGTH
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'),
+                "ERR_INVALID_RARITY_NAME"
+            );
+        }
+    }
+
+    // custom tiers have no default media
+    pub fn to_media_ipfs(&self) -> Option<String> {
         match *self {
             BoxRarity::Rare => {
-                String::from("bafkreibwmkcer2kp3kv67cydzhzzvzki7hdph5f4w7jeiep2r4s5dp7eb4")
+                Some(String::from("bafkreibwmkcer2kp3kv67cydzhzzvzki7hdph5f4w7jeiep2r4s5dp7eb4"))
             }
             BoxRarity::Epic => {
-                String::from("bafkreick7sjo4uzdy3sznvqjuafcds6f5p37apkggvvwkctptdy3qu2vbi")
+                Some(String::from("bafkreick7sjo4uzdy3sznvqjuafcds6f5p37apkggvvwkctptdy3qu2vbi"))
             }
             BoxRarity::Legendary => {
-                String::from("bafkreigdv4mnfrndcob64wrwbqoqce257v7bvtxp2flnyqg2onukpssyoq")
+                Some(String::from("bafkreigdv4mnfrndcob64wrwbqoqce257v7bvtxp2flnyqg2onukpssyoq"))
             }
+            BoxRarity::Custom(_) => None,
         }
     }
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
+impl RarityTier {
+    pub fn media(&self) -> Option<String> {
+        self.media.clone().or_else(|| self.rarity.to_media_ipfs())
+    }
+}
+
+const MAX_MEDIA_LENGTH: usize = 512;
+const MEDIA_HASH_LENGTH: usize = 32;
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BoxMedia {
+    // IPFS CID or URL of the box image
+    pub media: String,
+    // sha256 of the media, as in NEP-177
+    pub media_hash: Option<Base64VecU8>,
+}
+
+impl BoxMedia {
+    pub fn new(media: String) -> Self {
+        Self {
+            media,
+            media_hash: None,
+        }
+    }
+
+    pub fn assert_valid(&self) {
+        require!(
+            !self.media.is_empty() && self.media.len() <= MAX_MEDIA_LENGTH,
+            "ERR_INVALID_MEDIA"
+        );
+
+        require!(
+            self.media_hash
+                .as_ref()
+                .is_none_or(|hash| hash.0.len() == MEDIA_HASH_LENGTH),
+            "ERR_INVALID_MEDIA_HASH"
+        );
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
 pub struct Probability {
     pub numerator: u8,
@@ -104,7 +408,28 @@ impl Probability {
 
 #[cfg(test)]
 mod tests {
-    use crate::contract::types::Probability;
+    use near_sdk::borsh::BorshSerialize;
+    use near_sdk::serde_json;
+
+    use crate::contract::types::{BoxRarity, Probability};
+
+    #[test]
+    fn test_box_rarity_keeps_stored_layout() {
+        // the default tiers were a plain enum before, stored as a single byte
+        assert_eq!(BoxRarity::Rare.try_to_vec().unwrap(), vec![0]);
+        assert_eq!(BoxRarity::Epic.try_to_vec().unwrap(), vec![1]);
+        assert_eq!(BoxRarity::Legendary.try_to_vec().unwrap(), vec![2]);
+
+        assert_eq!(serde_json::to_string(&BoxRarity::Epic).unwrap(), "\"epic\"");
+        assert_eq!(
+            serde_json::from_str::<BoxRarity>("\"legendary\"").unwrap(),
+            BoxRarity::Legendary
+        );
+        assert_eq!(
+            serde_json::from_str::<BoxRarity>("\"mythic\"").unwrap(),
+            BoxRarity::Custom("mythic".to_string())
+        );
+    }
 
     #[test]
     fn test_probability_threshold() {
@@ -1,7 +1,9 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
 
-use super::{BoxId, QuestId};
+use super::{BoxId, QuestId, TokenId};
+
+const TOKEN_ID_DELIMITER: char = ':';
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -18,4 +20,15 @@ impl QuestBoxData {
             box_id,
         }
     }
-}
\ No newline at end of file
+
+    // boxes are exposed as NFTs with "quest_id:box_id" token ids
+    pub fn from_token_id(token_id: &TokenId) -> Option<Self> {
+        let (quest_id, box_id) = token_id.split_once(TOKEN_ID_DELIMITER)?;
+
+        Some(Self::new(quest_id.parse().ok()?, box_id.parse().ok()?))
+    }
+
+    pub fn to_token_id(&self) -> TokenId {
+        format!("{}{}{}", self.quest_id, TOKEN_ID_DELIMITER, self.box_id)
+    }
+}
@@ -0,0 +1,107 @@
+use ed25519_dalek::{PublicKey as Ed25519PublicKey, Signature, Verifier};
+use near_sdk::json_types::{Base64VecU8, U64};
+use near_sdk::{env, near_bindgen, Promise, PublicKey};
+
+use crate::{Contract, ContractExt};
+
+use super::json::JsonMintVoucher;
+use super::types::{BoxId, QuestId};
+
+/// Checks the ed25519 signature, near-sdk 4 doesn't expose the host function for it
+pub(crate) fn verify_voucher_signature(
+    public_key: &PublicKey,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    // the first byte of the key is its curve type
+    match (
+        Ed25519PublicKey::from_bytes(&public_key.as_bytes()[1..]),
+        Signature::from_bytes(signature),
+    ) {
+        (Ok(public_key), Ok(signature)) => public_key.verify(message, &signature).is_ok(),
+        _ => false,
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Registers the key the quest owner signs vouchers with, or stops accepting vouchers if no key is given
+    #[payable]
+    pub fn set_voucher_key(&mut self, quest_id: QuestId, public_key: Option<PublicKey>) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        quest.set_voucher_key(public_key);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    /// Mints the box of a voucher signed by the quest owner, the caller pays for its storage
+    #[payable]
+    pub fn redeem_voucher(&mut self, voucher: JsonMintVoucher, signature: Base64VecU8) -> BoxId {
+        let mut quest = self.quests.get(&voucher.quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", voucher.quest_id))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        let questbox = quest.redeem_voucher(&voucher, &signature.0);
+
+        self.quests.insert(&quest.id, &quest);
+
+        self.mint_boxes_per_owner(&questbox);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit =
+            env::storage_byte_cost() * ((storage_used_after - storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+
+        questbox.box_id
+    }
+
+    pub fn get_voucher_key(&self, quest_id: QuestId) -> Option<PublicKey> {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.voucher_key
+    }
+
+    pub fn is_voucher_redeemed(&self, quest_id: QuestId, nonce: U64) -> bool {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.used_voucher_nonces.contains(&nonce.0)
+    }
+}
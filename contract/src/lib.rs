@@ -1,17 +1,34 @@
+use std::collections::BTreeMap;
+
 use contract::enums::StorageKey;
 
-use contract::json::{JsonBox, JsonPoolRewards, Pagination};
+use contract::callbacks::{create_withdraw_nft_tokens_promise, create_withdraw_vested_promise};
+use contract::events::{
+    Event, NearPoolWithdrawalData, NearRangePoolWithdrawalData, NftBurnData, NftEvent, NftMintData,
+    NftPoolWithdrawalData, SponsoredPoolRefundData,
+};
+use contract::internal::charge_claim_storage;
+use contract::json::{
+    JsonBox, JsonBoxCapacity, JsonNftMint, JsonPool, JsonPoolOdds, JsonRarityTier,
+    JsonVestingPosition, Pagination,
+};
+use contract::json_types::json_ft_message::FtOnTransferMessage;
+use contract::json_types::json_mt_message::MtOnTransferMessage;
 use contract::json_types::json_nft_message::NftOnTransferMessage;
 use contract::json_types::json_quest::JsonQuest;
 use near_sdk::collections::UnorderedSet;
 use near_sdk::json_types::{U128, U64};
-use near_sdk::{collections::LookupMap, near_bindgen, AccountId, PanicOnDefault};
-use near_sdk::{env, require, Promise, PromiseOrValue, ONE_NEAR};
+use near_sdk::{collections::LookupMap, near_bindgen, AccountId, Balance, PanicOnDefault};
+use near_sdk::{assert_one_yocto, env, require, Promise, PromiseOrValue, PublicKey, ONE_NEAR};
 
 use contract::questbox::QuestBox;
+use contract::trusted_contracts::get_trusted_ft_contracts as get_trusted_ft_contracts_internal;
 use contract::trusted_contracts::get_trusted_nft_contracts as get_trusted_nft_contracts_internal;
 use contract::types::questbox_data::QuestBoxData;
-use contract::types::{BoxId, BoxRarity, Probability, QuestId, TokenId};
+use contract::types::{
+    BasisPoints, BoxId, BoxMedia, BoxRarity, BoxStatus, PendingReward, PoolId, Probability,
+    QuestId, RarityTier, TokenId, VestingSchedule,
+};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 
 use crate::contract::quest::Quest;
@@ -26,8 +43,19 @@ pub struct Contract {
     quests: LookupMap<QuestId, Quest>,
     quests_per_owner: LookupMap<AccountId, UnorderedSet<QuestId>>,
     questboxes_per_owner: LookupMap<AccountId, UnorderedSet<QuestBoxData>>,
+    // every box of every quest, so the tokens can be enumerated page by page
+    questboxes: UnorderedSet<QuestBoxData>,
     next_quest_id: QuestId,
     trusted_nft_contracts: UnorderedSet<AccountId>,
+    trusted_ft_contracts: UnorderedSet<AccountId>,
+    trusted_mt_contracts: UnorderedSet<AccountId>,
+    fee_bps: BasisPoints,
+    fee_treasury_id: AccountId,
+    fees_per_quest: LookupMap<QuestId, Balance>,
+    pending_fees: Balance,
+    pending_rewards: LookupMap<AccountId, Vec<PendingReward>>,
+    keyed_boxes: LookupMap<PublicKey, QuestBoxData>,
+    keyed_box_keys: LookupMap<QuestBoxData, PublicKey>,
 }
 
 #[near_bindgen]
@@ -38,8 +66,18 @@ impl Contract {
             quests: LookupMap::new(StorageKey::Quests),
             quests_per_owner: LookupMap::new(StorageKey::QuestsPerOwner),
             questboxes_per_owner: LookupMap::new(StorageKey::QuestBoxesPerOwner),
+            questboxes: UnorderedSet::new(StorageKey::QuestBoxes),
             trusted_nft_contracts: UnorderedSet::new(StorageKey::TrustedNftContracts),
+            trusted_ft_contracts: UnorderedSet::new(StorageKey::TrustedFtContracts),
+            trusted_mt_contracts: UnorderedSet::new(StorageKey::TrustedMtContracts),
             next_quest_id: 0,
+            fee_bps: 0,
+            fee_treasury_id: env::current_account_id(),
+            fees_per_quest: LookupMap::new(StorageKey::FeesPerQuest),
+            pending_fees: 0,
+            pending_rewards: LookupMap::new(StorageKey::PendingRewards),
+            keyed_boxes: LookupMap::new(StorageKey::KeyedBoxes),
+            keyed_box_keys: LookupMap::new(StorageKey::KeyedBoxKeys),
         };
 
         let default_trusted_nft_contracts = get_trusted_nft_contracts_internal();
@@ -50,6 +88,12 @@ impl Contract {
                 instance.trust_nft_contract(contract_id.clone());
             });
 
+        let default_trusted_ft_contracts = get_trusted_ft_contracts_internal();
+
+        default_trusted_ft_contracts.iter().for_each(|contract_id| {
+            instance.trust_ft_contract(contract_id.clone());
+        });
+
         return instance;
     }
 
@@ -69,6 +113,324 @@ impl Contract {
         self.quests.insert(&quest_id, &quest);
     }
 
+    #[payable]
+    pub fn add_sponsor(&mut self, quest_id: QuestId, account_id: AccountId) {
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.add_sponsor(&account_id);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit =
+            env::storage_byte_cost() * ((storage_used_after - storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be equal to {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn remove_sponsor(&mut self, quest_id: QuestId, account_id: AccountId) {
+        assert_one_yocto();
+
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.remove_sponsor(&account_id);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_refund = env::storage_byte_cost()
+            * (storage_used_before.saturating_sub(storage_used_after) as u128);
+
+        if storage_refund > 0 {
+            Promise::new(quest.owner_id.clone()).transfer(storage_refund);
+        }
+    }
+
+    /// Stops minting and adding rewards, so sponsors can get their unused rewards back, boxes stay claimable
+    #[payable]
+    pub fn close_quest(&mut self, quest_id: QuestId) {
+        assert_one_yocto();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.close();
+
+        self.quests.insert(&quest_id, &quest);
+    }
+
+    #[payable]
+    pub fn refund_sponsored_pool(&mut self, quest_id: QuestId, pool_id: PoolId) -> Promise {
+        assert_one_yocto();
+
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let (sponsor_id, amount, nft_tokens) = quest.refund_sponsored_pool(pool_id);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_refund = env::storage_byte_cost()
+            * (storage_used_before.saturating_sub(storage_used_after) as u128);
+
+        Event::SponsoredPoolRefund(vec![SponsoredPoolRefundData {
+            quest_id,
+            pool_id,
+            receiver_id: sponsor_id.clone(),
+            amount: U128(amount),
+            token_ids: nft_tokens
+                .as_ref()
+                .map(|(_, token_ids)| token_ids.clone())
+                .unwrap_or_default(),
+            storage_refund: U128(storage_refund),
+        }])
+        .emit();
+
+        match nft_tokens {
+            Option::None => Promise::new(sponsor_id).transfer(amount + storage_refund),
+            Option::Some((contract_id, token_ids)) => {
+                if storage_refund > 0 {
+                    Promise::new(sponsor_id.clone()).transfer(storage_refund);
+                }
+
+                create_withdraw_nft_tokens_promise(
+                    &sponsor_id,
+                    quest_id,
+                    &pool_id,
+                    &contract_id,
+                    &token_ids,
+                )
+            }
+        }
+    }
+
+    pub fn set_escrow_mode(&mut self, quest_id: QuestId, enabled: bool) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.set_escrow_mode(enabled);
+
+        self.quests.insert(&quest_id, &quest);
+    }
+
+    #[payable]
+    pub fn set_rarity_tiers(&mut self, quest_id: QuestId, tiers: Vec<JsonRarityTier>) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        quest.set_rarity_tiers(
+            tiers
+                .into_iter()
+                .map(|tier| {
+                    (
+                        RarityTier {
+                            rarity: tier.rarity,
+                            media: tier.media,
+                        },
+                        tier.probability,
+                    )
+                })
+                .collect(),
+        );
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn set_box_media(&mut self, quest_id: QuestId, rarity: BoxRarity, media: Option<BoxMedia>) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        quest.set_box_media(rarity, media);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn set_box_expiry(
+        &mut self,
+        quest_id: QuestId,
+        rarity: Option<BoxRarity>,
+        expires_at: Option<U64>,
+    ) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        quest.set_box_expiry(rarity, expires_at);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn set_max_boxes(
+        &mut self,
+        quest_id: QuestId,
+        rarity: Option<BoxRarity>,
+        max_boxes: Option<U64>,
+    ) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        quest.set_max_boxes(rarity, max_boxes);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn set_max_boxes_per_account(&mut self, quest_id: QuestId, max_boxes: Option<U64>) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        quest.set_max_boxes_per_account(max_boxes);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    pub fn sweep_expired_boxes(
+        &mut self,
+        quest_id: QuestId,
+        from_box_id: Option<BoxId>,
+        limit: Option<u64>,
+    ) -> Vec<BoxId> {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.sweep_expired_boxes(from_box_id.unwrap_or(0), limit.unwrap_or(u64::MAX))
+    }
+
+    pub fn set_transfers_enabled(&mut self, quest_id: QuestId, enabled: bool) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.set_transfers_enabled(enabled);
+
+        self.quests.insert(&quest_id, &quest);
+    }
+
     pub fn set_owner(&mut self, quest_id: QuestId, new_owner_id: AccountId) {
         let mut quest = self
             .quests
@@ -77,117 +439,520 @@ impl Contract {
 
         let current_owner_id = quest.owner_id.clone();
 
-        quest.set_owner(&new_owner_id);
+        quest.set_owner(&new_owner_id);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let account_hash = env::sha256_array(&new_owner_id.as_bytes());
+
+        //remove from old owner
+        let mut owner_quests =
+            self.quests_per_owner
+                .get(&current_owner_id)
+                .unwrap_or(UnorderedSet::new(StorageKey::QuestIdsPerOwner {
+                    account_hash,
+                }));
+
+        assert!(
+            owner_quests.contains(&quest_id),
+            "Quest doesn't belong to owner"
+        );
+
+        owner_quests.remove(&quest_id);
+
+        self.quests_per_owner
+            .insert(&current_owner_id, &owner_quests);
+
+        //add to new owner
+        let mut new_owner_quests =
+            self.quests_per_owner
+                .get(&new_owner_id)
+                .unwrap_or(UnorderedSet::new(StorageKey::QuestIdsPerOwner {
+                    account_hash,
+                }));
+
+        new_owner_quests.insert(&quest_id);
+        self.quests_per_owner
+            .insert(&new_owner_id, &new_owner_quests);
+    }
+
+    pub fn trust_nft_contract(&mut self, contract_id: AccountId) {
+        assert!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "ERR_FORBIDDEN"
+        );
+
+        let is_contract_trusted = self.trusted_nft_contracts.contains(&contract_id);
+
+        if !is_contract_trusted {
+            self.trusted_nft_contracts.insert(&contract_id);
+        } else {
+            panic!("Provided contract is already in the set");
+        }
+    }
+
+    pub fn untrust_nft_contract(&mut self, contract_id: AccountId) {
+        assert!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "Signer account is not the owner of the contract."
+        );
+
+        let is_contract_trusted = self.trusted_nft_contracts.contains(&contract_id);
+
+        if is_contract_trusted {
+            self.trusted_nft_contracts.remove(&contract_id);
+        } else {
+            panic!("Provided contract wasn't trusted before");
+        }
+    }
+
+    pub fn trust_ft_contract(&mut self, contract_id: AccountId) {
+        assert!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "ERR_FORBIDDEN"
+        );
+
+        let is_contract_trusted = self.trusted_ft_contracts.contains(&contract_id);
+
+        if !is_contract_trusted {
+            self.trusted_ft_contracts.insert(&contract_id);
+        } else {
+            panic!("Provided contract is already in the set");
+        }
+    }
+
+    pub fn untrust_ft_contract(&mut self, contract_id: AccountId) {
+        assert!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "Signer account is not the owner of the contract."
+        );
+
+        let is_contract_trusted = self.trusted_ft_contracts.contains(&contract_id);
+
+        if is_contract_trusted {
+            self.trusted_ft_contracts.remove(&contract_id);
+        } else {
+            panic!("Provided contract wasn't trusted before");
+        }
+    }
+
+    pub fn trust_mt_contract(&mut self, contract_id: AccountId) {
+        assert!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "ERR_FORBIDDEN"
+        );
+
+        let is_contract_trusted = self.trusted_mt_contracts.contains(&contract_id);
+
+        if !is_contract_trusted {
+            self.trusted_mt_contracts.insert(&contract_id);
+        } else {
+            panic!("Provided contract is already in the set");
+        }
+    }
+
+    pub fn untrust_mt_contract(&mut self, contract_id: AccountId) {
+        assert!(
+            env::predecessor_account_id() == env::current_account_id(),
+            "Signer account is not the owner of the contract."
+        );
+
+        let is_contract_trusted = self.trusted_mt_contracts.contains(&contract_id);
+
+        if is_contract_trusted {
+            self.trusted_mt_contracts.remove(&contract_id);
+        } else {
+            panic!("Provided contract wasn't trusted before");
+        }
+    }
+
+    #[payable]
+    pub fn add_near_reward(
+        &mut self,
+        quest_id: QuestId,
+        rarity: BoxRarity,
+        amount: U128,
+        capacity: U64,
+        weight: Option<U64>,
+    ) {
+        assert!(
+            MINIMAL_NEAR_REWARD <= amount.into(),
+            "The minimal reward in Near tokens is {} yocto",
+            MINIMAL_NEAR_REWARD
+        );
+
+        let reward_deposit = u128::from(amount) * (capacity.0 as u128);
+
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self
+            .quests
+            .get(&quest_id)
+            .expect(&format!("Quest with id {} wasn't found", quest_id.clone()));
+
+        quest.add_near_reward(rarity, amount, capacity, weight);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let fee = self.internal_take_fee(&quest, reward_deposit);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit =
+            env::storage_byte_cost() * ((storage_used_after - storage_used_before) as u128);
+
+        let total_deposit = storage_deposit + reward_deposit + fee;
+
+        assert!(
+            env::attached_deposit() >= total_deposit,
+            "Deposited amount must be equal to {} yocto",
+            total_deposit
+        );
+
+        let refund = env::attached_deposit() - total_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn add_near_range_reward(
+        &mut self,
+        quest_id: QuestId,
+        rarity: BoxRarity,
+        min_amount: U128,
+        max_amount: U128,
+        budget: U128,
+        weight: Option<U64>,
+    ) {
+        assert!(
+            MINIMAL_NEAR_REWARD <= min_amount.into(),
+            "The minimal reward in Near tokens is {} yocto",
+            MINIMAL_NEAR_REWARD
+        );
+
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.add_near_range_reward(rarity, min_amount, max_amount, budget, weight);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let fee = self.internal_take_fee(&quest, budget.into());
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit =
+            env::storage_byte_cost() * ((storage_used_after - storage_used_before) as u128);
+
+        let total_deposit = storage_deposit + u128::from(budget) + fee;
+
+        assert!(
+            env::attached_deposit() >= total_deposit,
+            "Deposited amount must be equal to {} yocto",
+            total_deposit
+        );
+
+        let refund = env::attached_deposit() - total_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn add_nft_mint_reward(
+        &mut self,
+        quest_id: QuestId,
+        rarity: BoxRarity,
+        mint: JsonNftMint,
+        capacity: U64,
+        weight: Option<U64>,
+    ) {
+        let mint_deposit = u128::from(mint.deposit) * (capacity.0 as u128);
+
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.add_nft_mint_reward(rarity, mint, capacity, weight);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit =
+            env::storage_byte_cost() * ((storage_used_after - storage_used_before) as u128);
+
+        let total_deposit = storage_deposit + mint_deposit;
+
+        assert!(
+            env::attached_deposit() >= total_deposit,
+            "Deposited amount must be equal to {} yocto",
+            total_deposit
+        );
+
+        let refund = env::attached_deposit() - total_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn add_bundle_reward(
+        &mut self,
+        quest_id: QuestId,
+        rarity: BoxRarity,
+        pool_ids: Vec<PoolId>,
+        weight: Option<U64>,
+    ) {
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.add_bundle_reward(rarity, pool_ids, weight);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be equal to {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn withdraw_near_pool(
+        &mut self,
+        quest_id: QuestId,
+        pool_id: PoolId,
+        count: U64,
+    ) -> Promise {
+        assert_one_yocto();
+
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let amount = quest.withdraw_near_pool(pool_id, count.into());
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_refund = env::storage_byte_cost()
+            * (storage_used_before.saturating_sub(storage_used_after) as u128);
+
+        Event::NearPoolWithdrawal(vec![NearPoolWithdrawalData {
+            quest_id,
+            pool_id,
+            receiver_id: quest.owner_id.clone(),
+            count,
+            amount: U128(amount),
+            storage_refund: U128(storage_refund),
+        }])
+        .emit();
+
+        Promise::new(quest.owner_id.clone()).transfer(amount + storage_refund)
+    }
+
+    #[payable]
+    pub fn set_pool_vesting(
+        &mut self,
+        quest_id: QuestId,
+        pool_id: PoolId,
+        vesting: Option<VestingSchedule>,
+    ) {
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let storage_used_before = env::storage_usage();
+
+        quest.set_pool_vesting(pool_id, vesting);
+
+        self.quests.insert(&quest_id, &quest);
+
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        assert!(
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be bigger than {} yocto",
+            storage_deposit
+        );
+
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    #[payable]
+    pub fn withdraw_vested(&mut self, quest_id: QuestId) -> Promise {
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let withdrawn = quest.withdraw_vested(&account_id);
+
+        create_withdraw_vested_promise(&account_id, quest_id, &withdrawn)
+    }
+
+    #[payable]
+    pub fn top_up_near_pool(&mut self, quest_id: QuestId, pool_id: PoolId, extra_capacity: U64) {
+        let storage_used_before = env::storage_usage();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let reward_deposit = quest.top_up_near_pool(pool_id, extra_capacity.into());
 
         self.quests.insert(&quest_id, &quest);
 
-        let account_hash = env::sha256_array(&new_owner_id.as_bytes());
+        let fee = self.internal_take_fee(&quest, reward_deposit);
 
-        //remove from old owner
-        let mut owner_quests =
-            self.quests_per_owner
-                .get(&current_owner_id)
-                .unwrap_or(UnorderedSet::new(StorageKey::QuestIdsPerOwner {
-                    account_hash,
-                }));
+        let storage_used_after = env::storage_usage();
+
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
+
+        let total_deposit = storage_deposit + reward_deposit + fee;
 
         assert!(
-            owner_quests.contains(&quest_id),
-            "Quest doesn't belong to owner"
+            env::attached_deposit() >= total_deposit,
+            "Deposited amount must be equal to {} yocto",
+            total_deposit
         );
 
-        owner_quests.remove(&quest_id);
-
-        self.quests_per_owner
-            .insert(&current_owner_id, &owner_quests);
+        let refund = env::attached_deposit() - total_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
 
-        //add to new owner
-        let mut new_owner_quests =
-            self.quests_per_owner
-                .get(&new_owner_id)
-                .unwrap_or(UnorderedSet::new(StorageKey::QuestIdsPerOwner {
-                    account_hash,
-                }));
+    #[payable]
+    pub fn set_pool_weight(&mut self, quest_id: QuestId, pool_id: PoolId, weight: Option<U64>) {
+        let storage_used_before = env::storage_usage();
 
-        new_owner_quests.insert(&quest_id);
-        self.quests_per_owner
-            .insert(&new_owner_id, &new_owner_quests);
-    }
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
 
-    pub fn trust_nft_contract(&mut self, contract_id: AccountId) {
-        assert!(
-            env::predecessor_account_id() == env::current_account_id(),
-            "ERR_FORBIDDEN"
-        );
+        quest.set_pool_weight(pool_id, weight);
 
-        let is_contract_trusted = self.trusted_nft_contracts.contains(&contract_id);
+        let storage_used_after = env::storage_usage();
 
-        if !is_contract_trusted {
-            self.trusted_nft_contracts.insert(&contract_id);
-        } else {
-            panic!("Provided contract is already in the set");
-        }
-    }
+        // resetting the weight releases storage, so nothing is charged then
+        let storage_deposit = env::storage_byte_cost()
+            * (storage_used_after.saturating_sub(storage_used_before) as u128);
 
-    pub fn untrust_nft_contract(&mut self, contract_id: AccountId) {
         assert!(
-            env::predecessor_account_id() == env::current_account_id(),
-            "Signer account is not the owner of the contract."
+            env::attached_deposit() >= storage_deposit,
+            "Deposited amount must be equal to {} yocto",
+            storage_deposit
         );
 
-        let is_contract_trusted = self.trusted_nft_contracts.contains(&contract_id);
-
-        if is_contract_trusted {
-            self.trusted_nft_contracts.remove(&contract_id);
-        } else {
-            panic!("Provided contract wasn't trusted before");
+        let refund = env::attached_deposit() - storage_deposit;
+        if refund > 1 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
         }
     }
 
     #[payable]
-    pub fn add_near_reward(
+    pub fn withdraw_near_range_pool(
         &mut self,
         quest_id: QuestId,
-        rarity: BoxRarity,
+        pool_id: PoolId,
         amount: U128,
-        capacity: U64,
-    ) {
-        assert!(
-            MINIMAL_NEAR_REWARD <= amount.into(),
-            "The minimal reward in Near tokens is {} yocto",
-            MINIMAL_NEAR_REWARD
-        );
-
-        let reward_deposit = u128::from(amount) * (capacity.0 as u128);
+    ) -> Promise {
+        assert_one_yocto();
 
         let storage_used_before = env::storage_usage();
 
-        let mut quest = self
-            .quests
-            .get(&quest_id)
-            .expect(&format!("Quest with id {} wasn't found", quest_id.clone()));
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
 
-        quest.add_near_reward(rarity, amount, capacity.into());
+        quest.withdraw_near_range_pool(pool_id, amount.into());
 
         self.quests.insert(&quest_id, &quest);
 
         let storage_used_after = env::storage_usage();
 
-        let storage_deposit =
-            env::storage_byte_cost() * ((storage_used_after - storage_used_before) as u128);
+        let storage_refund = env::storage_byte_cost()
+            * (storage_used_before.saturating_sub(storage_used_after) as u128);
 
-        let total_deposit = storage_deposit + reward_deposit.clone();
+        Event::NearRangePoolWithdrawal(vec![NearRangePoolWithdrawalData {
+            quest_id,
+            pool_id,
+            receiver_id: quest.owner_id.clone(),
+            amount,
+            storage_refund: U128(storage_refund),
+        }])
+        .emit();
 
-        assert!(
-            env::attached_deposit() >= total_deposit,
-            "Deposited amount must be equal to {} yocto",
-            total_deposit
-        );
+        Promise::new(quest.owner_id.clone()).transfer(amount.0 + storage_refund)
+    }
 
-        let refund = env::attached_deposit() - total_deposit;
-        if refund > 1 {
-            Promise::new(env::predecessor_account_id()).transfer(refund);
-        }
+    #[payable]
+    pub fn withdraw_nft_pool(
+        &mut self,
+        quest_id: QuestId,
+        pool_id: PoolId,
+        token_ids: Option<Vec<TokenId>>,
+    ) -> Promise {
+        assert_one_yocto();
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let (contract_id, token_ids) = quest.withdraw_nft_pool(pool_id, token_ids);
+
+        self.quests.insert(&quest_id, &quest);
+
+        Event::NftPoolWithdrawal(vec![NftPoolWithdrawalData {
+            quest_id,
+            pool_id,
+            receiver_id: quest.owner_id.clone(),
+            contract_id: contract_id.clone(),
+            token_ids: token_ids.clone(),
+        }])
+        .emit();
+
+        create_withdraw_nft_tokens_promise(
+            &quest.owner_id,
+            quest_id,
+            &pool_id,
+            &contract_id,
+            &token_ids,
+        )
     }
 
     #[payable]
@@ -208,7 +973,7 @@ impl Contract {
         let mut minted_boxes_ids = Vec::new();
 
         accounts.iter().for_each(|box_owner_id| {
-            let questbox = quest.mint(box_owner_id.clone(), rarity);
+            let questbox = quest.mint(box_owner_id.clone(), rarity.clone());
 
             self.mint_boxes_per_owner(&questbox);
 
@@ -270,30 +1035,119 @@ impl Contract {
         return questbox.box_id;
     }
 
-    //forbidden for now. we should implement returning the deposit to customer before allowing this method.
-    #[allow(dead_code)]
+    /// Deletes unclaimed boxes, the freed storage is refunded to the accounts which paid for the mint
     #[payable]
-    fn delete_boxes(&mut self, quest_id: QuestId, ids: Vec<BoxId>) {
+    pub fn delete_boxes(&mut self, quest_id: QuestId, ids: Vec<BoxId>) {
+        assert_one_yocto();
+
+        require!(!ids.is_empty(), "Ids can't be empty");
+
         let mut quest = self
             .quests
             .get(&quest_id)
             .expect(&format!("Quest with id {} wasn't found", quest_id.clone()));
 
-        let account_id = env::predecessor_account_id();
+        let mut refunds: BTreeMap<AccountId, Balance> = BTreeMap::new();
+        let mut burned_boxes = Vec::new();
+
+        ids.iter().for_each(|&box_id| {
+            let storage_used_before = env::storage_usage();
+
+            let questbox = quest.delete_box(box_id);
+            let questbox_data = QuestBoxData::new(quest.id, box_id);
+
+            self.internal_remove_questbox_from_owner(&questbox.owner_id, &questbox_data);
+            self.questboxes.remove(&questbox_data);
+
+            // keyed boxes are held by the contract, their key must not claim a deleted box
+            self.internal_remove_box_key(&questbox_data);
+
+            if !self.internal_owns_quest_boxes(&questbox.owner_id, quest.id) {
+                quest.users.remove(&questbox.owner_id);
+            }
+
+            let storage_used_after = env::storage_usage();
+
+            let minter_id = questbox.minter_id.unwrap_or_else(|| quest.owner_id.clone());
+
+            *refunds.entry(minter_id).or_default() += env::storage_byte_cost()
+                * (storage_used_before.saturating_sub(storage_used_after) as u128);
+
+            burned_boxes.push(NftBurnData {
+                owner_id: questbox.owner_id,
+                token_ids: vec![questbox_data.to_token_id()],
+            });
+        });
+
+        self.quests.insert(&quest.id, &quest);
+
+        NftEvent::NftBurn(burned_boxes).emit();
+
+        refunds
+            .into_iter()
+            .filter(|(_, refund)| *refund > 0)
+            .for_each(|(minter_id, refund)| {
+                Promise::new(minter_id).transfer(refund);
+            });
+    }
 
-        quest.delete_boxes(&ids);
+    /// Opens several boxes of the quest at once, returns the result for each box in the same order
+    #[payable]
+    pub fn claim_many(&mut self, quest_id: QuestId, box_ids: Vec<BoxId>) -> Promise {
+        let account_id = env::predecessor_account_id();
 
-        let mut owners_questboxes = self
+        let questboxes_per_owner = self
             .questboxes_per_owner
             .get(&account_id)
-            .expect("Owner doesn't have any quest boxes");
+            .expect("NO_BOXES_TO_CLAIM");
 
-        ids.iter().for_each(|&box_id| {
-            owners_questboxes.remove(&QuestBoxData::new(quest.id, box_id));
+        box_ids.iter().for_each(|box_id| {
+            require!(
+                questboxes_per_owner.contains(&QuestBoxData::new(quest_id, *box_id)),
+                "ERR_BOX_NOT_FOUND"
+            );
+        });
+
+        let mut quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        let (promise, vesting_storage) = quest.claim_many(&box_ids);
+
+        let escrow_storage = match quest.escrow_enabled {
+            true => self.internal_escrow_rewards(&quest, &account_id, &box_ids),
+            false => 0,
+        };
+
+        charge_claim_storage(vesting_storage + escrow_storage);
+
+        promise
+    }
+
+    /// Gifts an unclaimed box, the sender pays for the storage of the receiver's entry
+    #[payable]
+    pub fn transfer_box(&mut self, quest_id: QuestId, box_id: BoxId, receiver_id: AccountId) {
+        let questbox_data = QuestBoxData::new(quest_id, box_id);
+
+        self.assert_box_transferable(&questbox_data);
+
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
         });
 
-        self.questboxes_per_owner
-            .insert(&account_id, &owners_questboxes);
+        let questbox = quest.boxes.get(&box_id).expect("ERR_BOX_NOT_FOUND");
+
+        require!(
+            questbox.box_status == BoxStatus::NonClaimed,
+            "ERR_BOX_ALREADY_CLAIMED"
+        );
+
+        self.internal_transfer_box_with_deposit(
+            &env::predecessor_account_id(),
+            &receiver_id,
+            &questbox_data,
+            None,
+        );
     }
 
     #[payable]
@@ -318,7 +1172,16 @@ impl Contract {
             .get(&quest_id)
             .expect(&format!("Quest with id {} wasn't found", quest_id.clone()));
 
-        return quest.claim(box_id);
+        let (promise, vesting_storage) = quest.claim(box_id);
+
+        let escrow_storage = match quest.escrow_enabled {
+            true => self.internal_escrow_rewards(&quest, &account_id, &[box_id]),
+            false => 0,
+        };
+
+        charge_claim_storage(vesting_storage + escrow_storage);
+
+        promise
     }
 
     #[payable]
@@ -358,6 +1221,8 @@ impl Contract {
             previous_owner_id,
             token_id,
             parsed_nft_message.rarity,
+            parsed_nft_message.token_weight,
+            parsed_nft_message.weight,
         );
 
         self.quests.insert(&quest.id, &quest);
@@ -365,6 +1230,100 @@ impl Contract {
         return result;
     }
 
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // we're required to ensure that the predecessor account is whitelisted, since the function is public
+        let ft_account_id = env::predecessor_account_id();
+        require!(
+            self.trusted_ft_contracts.contains(&ft_account_id),
+            format!("ERR_FT_CONTRACT_NOT_TRUSTED. {}", &ft_account_id)
+        );
+
+        let parsed_message_result: Result<FtOnTransferMessage, near_sdk::serde_json::Error> =
+            near_sdk::serde_json::from_str(&msg);
+
+        if parsed_message_result.is_err() {
+            panic!("Error parsing message");
+        }
+
+        let parsed_ft_message = parsed_message_result.unwrap();
+
+        let mut quest = self
+            .quests
+            .get(&parsed_ft_message.quest_id)
+            .unwrap_or_else(|| {
+                env::panic_str(&format!(
+                    "Quest with id {} wasn't found",
+                    parsed_ft_message.quest_id
+                ))
+            });
+
+        let result = quest.ft_on_transfer(
+            sender_id,
+            amount,
+            parsed_ft_message.rarity,
+            parsed_ft_message.amount,
+            parsed_ft_message.capacity,
+            parsed_ft_message.weight,
+        );
+
+        self.quests.insert(&quest.id, &quest);
+
+        result
+    }
+
+    pub fn mt_on_transfer(
+        &mut self,
+        #[allow(unused_variables)] sender_id: AccountId,
+        previous_owner_ids: Vec<AccountId>,
+        token_ids: Vec<TokenId>,
+        amounts: Vec<U128>,
+        msg: String,
+    ) -> PromiseOrValue<Vec<U128>> {
+        // we're required to ensure that the predecessor account is whitelisted, since the function is public
+        let mt_account_id = env::predecessor_account_id();
+        require!(
+            self.trusted_mt_contracts.contains(&mt_account_id),
+            format!("ERR_MT_CONTRACT_NOT_TRUSTED. {}", &mt_account_id)
+        );
+
+        let parsed_message_result: Result<MtOnTransferMessage, near_sdk::serde_json::Error> =
+            near_sdk::serde_json::from_str(&msg);
+
+        if parsed_message_result.is_err() {
+            panic!("Error parsing message");
+        }
+
+        let parsed_mt_message = parsed_message_result.unwrap();
+
+        let mut quest = self
+            .quests
+            .get(&parsed_mt_message.quest_id)
+            .unwrap_or_else(|| {
+                env::panic_str(&format!(
+                    "Quest with id {} wasn't found",
+                    parsed_mt_message.quest_id
+                ))
+            });
+
+        let result = quest.mt_on_transfer(
+            previous_owner_ids,
+            token_ids,
+            amounts,
+            parsed_mt_message.rarity,
+            parsed_mt_message.amount,
+            parsed_mt_message.weight,
+        );
+
+        self.quests.insert(&quest.id, &quest);
+
+        result
+    }
+
     pub fn quests_per_owner(&self, account_id: AccountId) -> Vec<JsonQuest> {
         let quests_ids = self.quests_per_owner.get(&account_id);
 
@@ -430,7 +1389,9 @@ impl Contract {
                     return None;
                 }
 
-                let box_object = quest.unwrap().boxes.get(&quest_box.box_id);
+                let quest = quest.unwrap();
+
+                let box_object = quest.boxes.get(&quest_box.box_id);
 
                 if !box_object.is_some() {
                     return None;
@@ -438,7 +1399,7 @@ impl Contract {
 
                 let box_object_unwrapped = box_object.unwrap();
 
-                let box_json = JsonBox::from(box_object_unwrapped);
+                let box_json = quest.box_to_json(box_object_unwrapped);
 
                 return Some(box_json);
             })
@@ -472,7 +1433,9 @@ impl Contract {
                     return None;
                 }
 
-                let box_object = quest.unwrap().boxes.get(&quest_box.box_id);
+                let quest = quest.unwrap();
+
+                let box_object = quest.boxes.get(&quest_box.box_id);
 
                 if !box_object.is_some() {
                     return None;
@@ -480,7 +1443,7 @@ impl Contract {
 
                 let box_object_unwrapped = box_object.unwrap();
 
-                let box_json = JsonBox::from(box_object_unwrapped);
+                let box_json = quest.box_to_json(box_object_unwrapped);
 
                 return Some(box_json);
             })
@@ -490,7 +1453,7 @@ impl Contract {
     pub fn questboxes_total_supply(&self, quest_id: QuestId) -> U128 {
         let quest = self.quests.get(&quest_id).expect("Quest wasn't found");
 
-        return U128(quest.next_box_id);
+        quest.total_supply()
     }
 
     #[payable]
@@ -580,19 +1543,16 @@ impl Contract {
     }
 
     fn mint_boxes_per_owner(&mut self, questbox: &QuestBox) {
-        let boxes_per_owner_unwrapped = self.questboxes_per_owner.get(&questbox.owner_id);
-
-        let account_hash = env::sha256_array(&questbox.owner_id.as_bytes());
-        let mut boxes_per_owner = UnorderedSet::new(StorageKey::QuestBoxesData { account_hash });
-
-        if boxes_per_owner_unwrapped.is_some() {
-            boxes_per_owner = boxes_per_owner_unwrapped.unwrap();
-        }
+        let questbox_data = QuestBoxData::new(questbox.quest_id, questbox.box_id);
 
-        boxes_per_owner.insert(&QuestBoxData::new(questbox.quest_id, questbox.box_id));
+        self.internal_add_questbox_to_owner(&questbox.owner_id, &questbox_data);
+        self.questboxes.insert(&questbox_data);
 
-        self.questboxes_per_owner
-            .insert(&questbox.owner_id, &boxes_per_owner);
+        NftEvent::NftMint(vec![NftMintData {
+            owner_id: questbox.owner_id.clone(),
+            token_ids: vec![questbox_data.to_token_id()],
+        }])
+        .emit();
     }
 
     pub fn available_rewards(
@@ -600,28 +1560,65 @@ impl Contract {
         quest_id: QuestId,
         rarity: BoxRarity,
         pagination: Option<Pagination>,
-    ) -> Vec<JsonPoolRewards> {
-        let pagination = pagination.unwrap_or_default();
-
-        pagination.assert_valid();
-
+    ) -> Vec<JsonPool> {
         let quest = self
             .quests
             .get(&quest_id)
             .expect(&format!("Quest with id {} wasn't found", quest_id.clone()));
 
-        quest
-            .pool_ids_by_rarity
-            .get(&rarity)
-            .unwrap_or_default()
-            .iter()
-            .map(|pool_id| quest.pools.get(pool_id))
-            .flatten()
-            .filter(|pool| !pool.is_empty())
-            .take(pagination.take())
-            .skip(pagination.skip())
-            .map(|pool| pool.into())
-            .collect()
+        quest.available_rewards(rarity, pagination)
+    }
+
+    pub fn vesting_positions(
+        &self,
+        quest_id: QuestId,
+        account_id: AccountId,
+    ) -> Vec<JsonVestingPosition> {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.vesting_positions(&account_id)
+    }
+
+    pub fn get_sponsors(&self, quest_id: QuestId) -> Vec<AccountId> {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.sponsors.to_vec()
+    }
+
+    pub fn box_media(&self, quest_id: QuestId, rarity: BoxRarity) -> BoxMedia {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.rarity_media(&rarity)
+    }
+
+    pub fn rarity_tiers(&self, quest_id: QuestId) -> Vec<JsonRarityTier> {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.rarity_tiers()
+    }
+
+    pub fn box_capacity(&self, quest_id: QuestId, rarity: BoxRarity) -> JsonBoxCapacity {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.box_capacity(rarity)
+    }
+
+    pub fn pool_odds(&self, quest_id: QuestId, rarity: BoxRarity) -> Vec<JsonPoolOdds> {
+        let quest = self.quests.get(&quest_id).unwrap_or_else(|| {
+            env::panic_str(&format!("Quest with id {} wasn't found", quest_id.clone()))
+        });
+
+        quest.pool_odds(rarity)
     }
 
     pub fn rewards(
@@ -629,29 +1626,13 @@ impl Contract {
         quest_id: QuestId,
         rarity: BoxRarity,
         pagination: Option<Pagination>,
-    ) -> Vec<JsonPoolRewards> {
-        let pagination = pagination.unwrap_or_default();
-
-        pagination.assert_valid();
-
+    ) -> Vec<JsonPool> {
         let quest = self
             .quests
             .get(&quest_id)
             .expect(&format!("Quest with id {} wasn't found", quest_id.clone()));
 
-        pagination.assert_valid();
-
-        quest
-            .pool_ids_by_rarity
-            .get(&rarity)
-            .unwrap_or_default()
-            .iter()
-            .map(|pool_id| quest.pools.get(pool_id))
-            .flatten()
-            .take(pagination.take())
-            .skip(pagination.skip())
-            .map(|pool| pool.into())
-            .collect()
+        quest.rewards(rarity, pagination)
     }
 
     pub fn get_users(&self, quest_id: QuestId, pagination: Option<Pagination>) -> Vec<AccountId> {
@@ -669,6 +1650,14 @@ impl Contract {
     pub fn get_trusted_nft_contracts(&self) -> Vec<AccountId> {
         self.trusted_nft_contracts.to_vec()
     }
+
+    pub fn get_trusted_ft_contracts(&self) -> Vec<AccountId> {
+        self.trusted_ft_contracts.to_vec()
+    }
+
+    pub fn get_trusted_mt_contracts(&self) -> Vec<AccountId> {
+        self.trusted_mt_contracts.to_vec()
+    }
 }
 
 #[cfg(test)]
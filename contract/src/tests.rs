@@ -1,15 +1,39 @@
 #![allow(unused)]
 #[cfg(test)]
-use near_sdk::json_types::{U128, U64};
-use near_sdk::test_utils::VMContextBuilder;
-use near_sdk::{testing_env, AccountId, ONE_NEAR};
+use ed25519_dalek::{Keypair, SecretKey, Signer};
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, UnorderedSet};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::mock::VmAction;
+use near_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
+use near_sdk::{
+    env, testing_env, AccountId, PromiseOrValue, PromiseResult, PublicKey, RuntimeFeesConfig,
+    VMConfig, ONE_NEAR,
+};
 use utils::create_quest;
 
+use std::collections::{BTreeSet, HashSet};
 use std::str::FromStr;
 
-use crate::contract::json::{JsonBoxStatus, JsonPoolRewards, JsonReward, Pagination};
+use crate::contract::enums::StorageKey;
+
+use crate::contract::json::{
+    JsonBoxCapacity, JsonBoxSale, JsonBoxStatus, JsonMintVoucher, JsonNftMint, JsonPendingReward,
+    JsonPool, JsonPoolOdds, JsonPoolRewards, JsonQuestFees, JsonRarityTier, JsonReward,
+    JsonVestingPosition, Pagination,
+};
+use crate::contract::migration::{
+    LegacyContract, LegacyNonFungibleTokenPoolKind, LegacyPool, LegacyPoolKind, LegacyQuest,
+};
+use crate::contract::pools::NearPoolKind;
 use crate::contract::quest::Quest;
-use crate::contract::types::{BoxRarity, BoxStatus, Probability, Reward};
+use crate::contract::questbox::QuestBox;
+use crate::contract::types::questbox_data::QuestBoxData;
+use crate::contract::types::token_metadata::TokenMetadata;
+use crate::contract::types::{
+    BoxId, BoxMedia, BoxRarity, BoxStatus, PendingReward, PoolId, Probability, QuestId, Reward,
+    VestingSchedule,
+};
 
 mod integration_tests;
 mod utils;
@@ -44,6 +68,14 @@ fn nft3() -> AccountId {
     AccountId::from_str("nft_contract_3").unwrap()
 }
 
+fn ft() -> AccountId {
+    AccountId::from_str("ft_contract").unwrap()
+}
+
+fn mt() -> AccountId {
+    AccountId::from_str("mt_contract").unwrap()
+}
+
 fn testnet_user() -> AccountId {
     AccountId::from_str("test.testnet").unwrap()
 }
@@ -173,7 +205,13 @@ fn test_set_probability_bigger_than_one_panic() {
 fn test_add_small_near_pool_with_panic() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 20), U64(10));
+    contract.add_near_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR / 20),
+        U64(10),
+        None,
+    );
 }
 
 #[test]
@@ -187,7 +225,7 @@ fn test_add_near_pool_succeeds() {
     const AMOUNT: U128 = U128(ONE_NEAR);
     const CAPACITY: U64 = U64(3);
 
-    contract.add_near_reward(quest.id, POOL_RARITY, AMOUNT, CAPACITY);
+    contract.add_near_reward(quest.id, POOL_RARITY, AMOUNT, CAPACITY, None);
 
     let quest_modified = contract.quests.get(&quest.id).expect("Quest should exist");
 
@@ -219,7 +257,7 @@ fn test_add_big_near_pool() {
     const AMOUNT: U128 = U128(ONE_NEAR);
     const CAPACITY: U64 = U64(10_000);
 
-    contract.add_near_reward(quest.id, POOL_RARITY, AMOUNT, CAPACITY);
+    contract.add_near_reward(quest.id, POOL_RARITY, AMOUNT, CAPACITY, None);
 
     let quest_modified = contract.quests.get(&quest.id).expect("Quest should exist");
 
@@ -246,8 +284,8 @@ fn test_add_big_near_pool() {
 fn test_add_multiple_near_pools_succeeds() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5));
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
 
     const FIRST_POOL_ID: u32 = 0;
     const SECOND_POOL_ID: u32 = 1;
@@ -262,136 +300,104 @@ fn test_add_multiple_near_pools_succeeds() {
 }
 
 #[test]
-fn test_add_nft_pool_succeeds() {
+fn test_withdraw_near_pool_succeeds() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.trust_nft_contract(nft().clone());
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
 
-    testing_env!(context.predecessor_account_id(nft()).build());
+    testing_env!(context.attached_deposit(1).build());
 
-    contract.nft_on_transfer(
-        nft(),
-        owner(),
-        "some_token".to_string(),
-        String::from(
-            r#"
-        {
-            "quest_id": 0,
-            "rarity": "rare"
+    contract.withdraw_near_pool(quest.id, 0, U64(2));
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::Near {
+            amount: U128(ONE_NEAR),
+            available: 3,
+            total: 3,
         }
-        "#,
-        ),
     );
 }
 
 #[test]
-fn test_add_multiple_nft_pool_succeeds() {
-    let (mut contract, mut context, quest) = setup(None, None, None);
-
-    contract.trust_nft_contract(nft().clone());
-    contract.trust_nft_contract(nft2().clone());
+fn test_withdraw_whole_near_pool_removes_pool() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    testing_env!(context.predecessor_account_id(nft()).build());
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
 
-    contract.nft_on_transfer(
-        nft(),
-        owner(),
-        "some_token".to_string(),
-        String::from(
-            r#"
-        {
-            "quest_id": 0,
-            "rarity": "rare"
-        }
-        "#,
-        ),
-    );
-    contract.nft_on_transfer(
-        owner(),
-        owner(),
-        "some_token_2".to_string(),
-        String::from(
-            r#"
-        {
-            "quest_id": 0,
-            "rarity": "rare"
-        }
-        "#,
-        ),
-    );
-}
+    testing_env!(context.attached_deposit(1).build());
 
-#[test]
-#[should_panic(expected = "ERR_NFT_CONTRACT_NOT_TRUSTED")]
-fn test_add_non_whitelisted_nft_pool_with_panic() {
-    let (mut contract, mut context, quest) = setup(None, None, Some(nft3()));
+    contract.withdraw_near_pool(quest.id, 0, U64(5));
 
-    contract.nft_on_transfer(
-        owner(),
-        owner(),
-        "some_token".to_string(),
-        String::from(
-            r#"
-        {
-            "quest_id": 0,
-            "rarity": "rare"
-        }
-        "#,
-        ),
-    );
+    assert_eq!(contract.rewards(quest.id, BoxRarity::Rare, None).len(), 0);
+    assert!(quest.pools.get(&0).is_none());
 }
 
 #[test]
-fn test_available_near_rewards_amount() {
+fn test_withdraw_near_pool_keeps_claimed_rewards() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
-    let rewards = quest.available_rewards(BoxRarity::Rare, None);
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
 
-    assert_eq!(rewards.len(), 1);
+    contract.claim(quest.id, box_id);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5));
+    testing_env!(context.predecessor_account_id(owner()).build());
 
-    let rewards = quest.available_rewards(BoxRarity::Rare, None);
+    contract.withdraw_near_pool(quest.id, 0, U64(1));
 
-    assert_eq!(rewards.len(), 2);
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Epic, U128(ONE_NEAR), U64(5));
+    assert_eq!(
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::Near {
+            amount: U128(ONE_NEAR),
+            available: 0,
+            total: 1,
+        }
+    );
+}
 
-    let rewards = quest.available_rewards(BoxRarity::Rare, None);
-    assert_eq!(rewards.len(), 2);
+#[test]
+#[should_panic(expected = "ERR_POOL_NOT_AVAILABLE")]
+fn test_withdraw_near_pool_above_available_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    let rewards = quest.available_rewards(BoxRarity::Epic, None);
-    assert_eq!(rewards.len(), 1);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_near_pool(quest.id, 0, U64(3));
 }
 
 #[test]
-fn test_available_near_rewards_data() {
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_withdraw_near_pool_by_regular_user_with_panic() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5));
-
-    let rewards = quest.available_rewards(BoxRarity::Rare, None);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
 
-    let reward = rewards.get(0).unwrap().to_owned();
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
 
-    assert_eq!(
-        reward,
-        JsonPoolRewards::Near {
-            amount: U128(ONE_NEAR),
-            available: 5,
-            total: 5,
-        }
-    );
+    contract.withdraw_near_pool(quest.id, 0, U64(1));
 }
 
 #[test]
-fn test_available_nft_rewards_amount_for_different_contracts() {
+#[should_panic(expected = "ERR_NOT_NEAR_POOL")]
+fn test_withdraw_near_pool_from_nft_pool_with_panic() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.trust_nft_contract(nft().clone());
-    contract.trust_nft_contract(nft2().clone());
+    contract.trust_nft_contract(nft());
 
     testing_env!(context.predecessor_account_id(nft()).build());
 
@@ -409,406 +415,4610 @@ fn test_available_nft_rewards_amount_for_different_contracts() {
         ),
     );
 
-    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
-    assert_eq!(rewards.len(), 1);
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(owner())
+        .build());
 
-    testing_env!(context.predecessor_account_id(nft2()).build());
-    contract.nft_on_transfer(
-        nft2(),
-        owner(),
-        "another_token".to_string(),
-        String::from(
-            r#"
-        {
-            "quest_id": 0,
-            "rarity": "rare"
-        }
-        "#,
-        ),
+    contract.withdraw_near_pool(quest.id, 0, U64(1));
+}
+
+#[test]
+fn test_add_near_range_pool_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_range_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR / 10),
+        U128(ONE_NEAR),
+        U128(3 * ONE_NEAR),
+        None,
     );
 
-    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
-    assert_eq!(rewards.len(), 2);
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::NearRange {
+            min_amount: U128(ONE_NEAR / 10),
+            max_amount: U128(ONE_NEAR),
+            available: U128(3 * ONE_NEAR),
+            total: U128(3 * ONE_NEAR),
+        }
+    );
 }
 
 #[test]
-fn test_available_nft_rewards_amount_for_same_contract() {
-    let (mut contract, mut context, quest) = setup(None, None, None);
+#[should_panic(expected = "ERR_MIN_AMOUNT_BIGGER_THAN_MAX_AMOUNT")]
+fn test_add_near_range_pool_with_inverted_bounds_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.trust_nft_contract(nft().clone());
+    contract.add_near_range_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR),
+        U128(ONE_NEAR / 10),
+        U128(3 * ONE_NEAR),
+        None,
+    );
+}
 
-    testing_env!(context.predecessor_account_id(nft()).build());
+#[test]
+#[should_panic(expected = "The minimal reward in Near tokens is 100000000000000000000000 yocto")]
+fn test_add_small_near_range_pool_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.nft_on_transfer(
-        nft(),
-        owner(),
-        "some_token".to_string(),
-        String::from(
-            r#"
-        {
-            "quest_id": 0,
-            "rarity": "rare"
-        }
-        "#,
-        ),
+    contract.add_near_range_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR / 20),
+        U128(ONE_NEAR),
+        U128(3 * ONE_NEAR),
+        None,
     );
+}
 
-    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
-    assert_eq!(rewards.len(), 1);
+#[test]
+fn test_claim_near_range_reward_spreads_over_range() {
+    let (mut contract, mut context, quest) = setup(Some(25), None, None);
 
-    testing_env!(context.predecessor_account_id(nft()).build());
+    const MIN_AMOUNT: u128 = ONE_NEAR / 10;
+    const MAX_AMOUNT: u128 = 5 * ONE_NEAR;
 
-    contract.nft_on_transfer(
-        nft(),
-        owner(),
-        "some_token_2".to_string(),
-        String::from(
-            r#"
-        {
-            "quest_id": 0,
-            "rarity": "rare"
-        }
-        "#,
-        ),
+    contract.add_near_range_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(MIN_AMOUNT),
+        U128(MAX_AMOUNT),
+        U128(20 * ONE_NEAR),
+        None,
     );
 
-    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
-    assert_eq!(rewards.len(), 1);
+    let box_ids = contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(); 5]);
+
+    let amounts: Vec<u128> = box_ids
+        .iter()
+        .enumerate()
+        .map(|(index, box_id)| {
+            testing_env!(context
+                .attached_deposit(1)
+                .predecessor_account_id(user1())
+                .random_seed([index as u8 * 31 + 5; 32])
+                .build());
+
+            contract.claim(quest.id, *box_id);
+
+            let claimed_box = contract
+                .questboxes_per_owner(user1(), quest.id, None)
+                .into_iter()
+                .find(|questbox| questbox.box_id == *box_id)
+                .unwrap();
+
+            match claimed_box.box_status {
+                JsonBoxStatus::Claimed {
+                    reward: JsonReward::Near { amount },
+                } => amount.0,
+                _ => panic!("Box should be claimed with Near reward"),
+            }
+        })
+        .collect();
+
+    // a u64 random would keep every amount within 0.00002 NEAR of the minimum
+    assert!(amounts
+        .iter()
+        .all(|amount| *amount >= MIN_AMOUNT && *amount <= MAX_AMOUNT));
+    assert!(amounts
+        .iter()
+        .any(|amount| *amount > MIN_AMOUNT + ONE_NEAR / 10));
+    assert!(amounts.windows(2).any(|pair| pair[0] != pair[1]));
 }
 
 #[test]
-fn test_available_nft_rewards_amount_for_same_contract_and_different_rarity() {
-    let (mut contract, mut context, quest) = setup(None, None, None);
-
-    contract.trust_nft_contract(nft().clone());
+fn test_claim_near_range_reward_never_overspends_budget() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    testing_env!(context.predecessor_account_id(nft()).build());
+    const MIN_AMOUNT: u128 = ONE_NEAR / 10;
+    const MAX_AMOUNT: u128 = ONE_NEAR;
+    const BUDGET: u128 = 2 * ONE_NEAR + ONE_NEAR / 20;
 
-    contract.nft_on_transfer(
-        nft(),
-        owner(),
-        "some_token".to_string(),
-        String::from(
-            r#"
-        {
-            "quest_id": 0,
-            "rarity": "rare"
-        }
-        "#,
-        ),
+    contract.add_near_range_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(MIN_AMOUNT),
+        U128(MAX_AMOUNT),
+        U128(BUDGET),
+        None,
     );
 
-    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
-    assert_eq!(rewards.len(), 1);
+    let box_ids = contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(); 30]);
 
-    testing_env!(context.predecessor_account_id(nft()).build());
-    contract.nft_on_transfer(
-        nft(),
-        owner(),
-        "some_token_2".to_string(),
-        String::from(
-            r#"
+    let mut claimed_amount: u128 = 0;
+
+    for (index, box_id) in box_ids.iter().enumerate() {
+        if contract
+            .available_rewards(quest.id, BoxRarity::Rare, None)
+            .is_empty()
         {
-            "quest_id": 0,
-            "rarity": "epic"
+            break;
         }
-        "#,
-        ),
-    );
 
-    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
-    assert_eq!(rewards.len(), 1);
-    let rewards = contract.available_rewards(quest.id, BoxRarity::Epic, None);
-    assert_eq!(rewards.len(), 1);
+        testing_env!(context
+            .attached_deposit(1)
+            .predecessor_account_id(user1())
+            .random_seed([index as u8 * 7 + 1; 32])
+            .build());
+
+        contract.claim(quest.id, *box_id);
+
+        let claimed_box = contract
+            .questboxes_per_owner(user1(), quest.id, Some(Pagination::new(1, 40)))
+            .into_iter()
+            .find(|questbox| questbox.box_id == *box_id)
+            .unwrap();
+
+        match claimed_box.box_status {
+            JsonBoxStatus::Claimed {
+                reward: JsonReward::Near { amount },
+            } => {
+                assert!(MIN_AMOUNT <= amount.0 && amount.0 <= MAX_AMOUNT);
+
+                claimed_amount += amount.0;
+            }
+            _ => panic!("Box should be claimed with Near reward"),
+        }
+    }
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    match rewards.first().unwrap().rewards.to_owned() {
+        JsonPoolRewards::NearRange {
+            available, total, ..
+        } => {
+            assert!(available.0 < MIN_AMOUNT);
+            assert_eq!(claimed_amount + available.0, total.0);
+            assert_eq!(total.0, BUDGET);
+        }
+        _ => panic!("Pool should be Near range pool"),
+    }
 }
 
 #[test]
-fn test_available_nft_rewards_data_for_same_contract() {
-    let (mut contract, mut context, quest) = setup(None, None, None);
+fn test_withdraw_near_range_pool_dust_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.trust_nft_contract(nft().clone());
+    contract.add_near_range_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR),
+        U128(ONE_NEAR),
+        U128(ONE_NEAR + ONE_NEAR / 2),
+        None,
+    );
 
-    testing_env!(context.predecessor_account_id(nft()).build());
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
-    contract.nft_on_transfer(
-        nft(),
-        owner(),
-        "some_token".to_string(),
-        format!(
-            r#"
-        {{
-            "quest_id": {},
-            "rarity": "rare"
-        }}
-        "#,
-            quest.id
-        ),
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    // only dust is left, which isn't enough for another claim
+    assert_eq!(
+        contract
+            .available_rewards(quest.id, BoxRarity::Rare, None)
+            .len(),
+        0
     );
-    contract.nft_on_transfer(
-        nft(),
-        owner(),
-        "some_token_2".to_string(),
-        format!(
-            r#"
-        {{
-            "quest_id": {},
-            "rarity": "rare"
-        }}
-        "#,
-            quest.id
-        ),
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+
+    contract.withdraw_near_range_pool(quest.id, 0, U128(ONE_NEAR / 2));
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::NearRange {
+            min_amount: U128(ONE_NEAR),
+            max_amount: U128(ONE_NEAR),
+            available: U128(0),
+            total: U128(ONE_NEAR),
+        }
     );
+}
 
-    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
+#[test]
+#[should_panic(expected = "ERR_POOL_NOT_AVAILABLE")]
+fn test_withdraw_near_range_pool_above_available_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    let reward = rewards.get(0).unwrap().to_owned();
+    contract.add_near_range_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR / 10),
+        U128(ONE_NEAR),
+        U128(ONE_NEAR),
+        None,
+    );
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_near_range_pool(quest.id, 0, U128(2 * ONE_NEAR));
+}
+
+fn nft_mint() -> JsonNftMint {
+    JsonNftMint {
+        contract_id: nft(),
+        metadata: nft_mint_metadata(),
+        deposit: U128(ONE_NEAR / 100),
+    }
+}
+
+fn nft_mint_metadata() -> TokenMetadata {
+    TokenMetadata {
+        title: Some("Mystery reward".to_string()),
+        description: None,
+        media: Some("bafkreibwmkcer2kp3kv67cydzhzzvzki7hdph5f4w7jeiep2r4s5dp7eb4".to_string()),
+        media_hash: None,
+        copies: None,
+        issued_at: None,
+        expires_at: None,
+        starts_at: None,
+        updated_at: None,
+        extra: None,
+        reference: None,
+        reference_hash: None,
+    }
+}
+
+#[test]
+fn test_add_nft_mint_pool_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_nft_mint_reward(quest.id, BoxRarity::Epic, nft_mint(), U64(100), None);
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Epic, None);
 
     assert_eq!(
-        reward,
-        JsonPoolRewards::NonFungibleToken {
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::NonFungibleTokenMint {
             contract_id: nft(),
-            token_ids: vec!["some_token".to_string(), "some_token_2".to_string()],
-            total: 2,
+            metadata: Box::new(nft_mint_metadata()),
+            available: 100,
+            total: 100,
         }
     );
 }
 
 #[test]
-fn test_mint_succeeds() {
+#[should_panic(expected = "Deposited amount must be equal to")]
+fn test_add_nft_mint_pool_without_deposit_panic() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
+
+    contract.add_nft_mint_reward(quest.id, BoxRarity::Epic, nft_mint(), U64(100), None);
 }
 
 #[test]
-fn test_delete_boxes_succeeds() {
-    let (mut contract, mut context, _) = setup(None, None, Some(user1()));
+fn test_claim_nft_mint_reward_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    let mut quest = create_quest(&mut contract, &mut context, Some(10));
+    contract.add_nft_mint_reward(quest.id, BoxRarity::Rare, nft_mint(), U64(2), None);
 
-    let box_id = contract.mint(quest.id, user1(), BoxRarity::Epic);
-    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(2));
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
 
-    contract.delete_boxes(quest.id, vec![0]);
+    contract.claim(quest.id, box_id);
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(1));
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
 
-    assert!(quest.boxes.get(&box_id).is_none());
+    assert_eq!(
+        boxes.first().unwrap().box_status,
+        JsonBoxStatus::Claimed {
+            reward: JsonReward::NonFungibleToken {
+                contract_id: nft(),
+                token_id: "0:0:0".to_string(),
+            },
+        }
+    );
 }
 
 #[test]
-fn test_mint_many_succeeds() {
+fn test_failed_nft_mint_reward_doesnt_reuse_token_id() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user2()]);
+    contract.add_nft_mint_reward(quest.id, BoxRarity::Rare, nft_mint(), U64(1), None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    let reward = claimed_reward(&contract, quest.id, box_id);
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed]
+    );
+
+    contract.transfer_reward_callback(quest.id, user1(), box_id, 0, reward);
+
+    assert_eq!(
+        contract
+            .available_rewards(quest.id, BoxRarity::Rare, None)
+            .len(),
+        1
+    );
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert_eq!(
+        boxes.first().unwrap().box_status,
+        JsonBoxStatus::Claimed {
+            reward: JsonReward::NonFungibleToken {
+                contract_id: nft(),
+                token_id: "0:0:1".to_string(),
+            },
+        }
+    );
+}
+
+fn add_nft_tokens(contract: &mut Contract, context: &mut VMContextBuilder, token_ids: Vec<&str>) {
+    testing_env!(context.predecessor_account_id(owner()).build());
+
+    contract.trust_nft_contract(nft());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    token_ids.iter().for_each(|token_id| {
+        contract.nft_on_transfer(
+            nft(),
+            owner(),
+            token_id.to_string(),
+            String::from(
+                r#"
+            {
+                "quest_id": 0,
+                "rarity": "rare"
+            }
+            "#,
+            ),
+        );
+    });
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+}
+
+fn add_bundle_of_near_and_nft(contract: &mut Contract, context: &mut VMContextBuilder) {
+    add_nft_tokens(contract, context, vec!["token_1", "token_2"]);
+
+    contract.add_near_reward(0, BoxRarity::Rare, U128(ONE_NEAR), U64(3), None);
+
+    contract.add_bundle_reward(0, BoxRarity::Rare, vec![0, 1], None);
+}
+
+fn claimed_reward(contract: &Contract, quest_id: u64, box_id: u128) -> Reward {
+    let quest = contract.quests.get(&quest_id).unwrap();
+
+    match quest.boxes.get(&box_id).unwrap().box_status {
+        BoxStatus::Claimed { reward } => reward.unwrap(),
+        _ => panic!("Box should be claimed"),
+    }
 }
 
 #[test]
-#[should_panic(expected = "Accounts can't be empty")]
-fn test_mint_many_without_accounts_panic() {
+fn test_add_bundle_pool_succeeds() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.mint_many(quest.id, BoxRarity::Rare, vec![]);
+    add_bundle_of_near_and_nft(&mut contract, &mut context);
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards,
+        vec![JsonPool {
+            pool_id: 2,
+            sponsor_id: None,
+            vesting: None,
+            rewards: JsonPoolRewards::Bundle {
+                rewards: vec![
+                    JsonPool {
+                        pool_id: 0,
+                        sponsor_id: None,
+                        vesting: None,
+                        rewards: JsonPoolRewards::NonFungibleToken {
+                            contract_id: nft(),
+                            token_ids: vec!["token_1".to_string(), "token_2".to_string()],
+                            token_weights: Default::default(),
+                            total: 2,
+                        },
+                    },
+                    JsonPool {
+                        pool_id: 1,
+                        sponsor_id: None,
+                        vesting: None,
+                        rewards: JsonPoolRewards::Near {
+                            amount: U128(ONE_NEAR),
+                            available: 3,
+                            total: 3,
+                        },
+                    },
+                ],
+                available: 2,
+            },
+        }]
+    );
 }
 
 #[test]
-fn test_total_supply_default() {
+fn test_add_nft_to_bundled_pool_keeps_it_hidden() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    assert_eq!(contract.questboxes_total_supply(quest.id), U128(0));
+    add_bundle_of_near_and_nft(&mut contract, &mut context);
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "token_3".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(rewards.len(), 1);
+    assert_eq!(rewards.first().unwrap().pool_id, 2);
 }
 
 #[test]
-fn test_total_supply_increases() {
+#[should_panic(expected = "Pool 1 is already a part of some bundle")]
+fn test_add_bundle_pool_with_bundled_pool_panic() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    add_bundle_of_near_and_nft(&mut contract, &mut context);
 
-    assert_eq!(contract.questboxes_total_supply(quest.id), U128(1));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(1), None);
 
-    contract.mint(quest.id, user1(), BoxRarity::Rare);
-    contract.mint(quest.id, user2(), BoxRarity::Epic);
+    contract.add_bundle_reward(quest.id, BoxRarity::Rare, vec![1, 3], None);
+}
 
-    assert_eq!(contract.questboxes_total_supply(quest.id), U128(3));
+#[test]
+#[should_panic(expected = "Bundle can't contain another bundle")]
+fn test_add_bundle_pool_with_nested_bundle_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.mint_many(quest.id, BoxRarity::Legendary, vec![user1(), user2()]);
+    add_bundle_of_near_and_nft(&mut contract, &mut context);
 
-    assert_eq!(contract.questboxes_total_supply(quest.id), U128(5));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(1), None);
+
+    contract.add_bundle_reward(quest.id, BoxRarity::Rare, vec![2, 3], None);
 }
 
 #[test]
-fn test_supply_for_owner_default() {
+#[should_panic(expected = "Bundle must contain at least 2 pools")]
+fn test_add_bundle_pool_with_single_pool_panic() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(0));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(1), None);
+
+    contract.add_bundle_reward(quest.id, BoxRarity::Rare, vec![0], None);
 }
 
 #[test]
-fn test_supply_for_owner_increases() {
+fn test_claim_bundle_reward_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_bundle_of_near_and_nft(&mut contract, &mut context);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    // the token of the bundle is picked at random
+    let token_id = match claimed_reward(&contract, quest.id, box_id) {
+        Reward::Bundle { rewards } => match &rewards.first().unwrap().reward {
+            Reward::NonFungibleToken { token_id, .. } => token_id.clone(),
+            _ => panic!("Bundle should start with the token"),
+        },
+        _ => panic!("Reward should be a bundle"),
+    };
+
+    assert!(token_id == "token_1" || token_id == "token_2");
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert_eq!(
+        boxes.first().unwrap().box_status,
+        JsonBoxStatus::Claimed {
+            reward: JsonReward::Bundle {
+                rewards: vec![
+                    JsonReward::NonFungibleToken {
+                        contract_id: nft(),
+                        token_id,
+                    },
+                    JsonReward::Near {
+                        amount: U128(ONE_NEAR),
+                    },
+                ],
+            },
+        }
+    );
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    match rewards.first().unwrap().rewards.to_owned() {
+        JsonPoolRewards::Bundle { available, .. } => assert_eq!(available, 1),
+        _ => panic!("Pool should be a bundle"),
+    }
+}
+
+#[test]
+fn test_transfer_bundle_reward_callback_puts_back_failed_parts() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_bundle_of_near_and_nft(&mut contract, &mut context);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    let reward = claimed_reward(&contract, quest.id, box_id);
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed, PromiseResult::Successful(vec![])]
+    );
+
+    let result = contract.transfer_reward_callback(quest.id, user1(), box_id, 2, reward);
+
+    assert_eq!(
+        result.unwrap().2,
+        JsonReward::Bundle {
+            rewards: vec![JsonReward::Near {
+                amount: U128(ONE_NEAR),
+            }],
+        }
+    );
+
+    // NFT went back to its pool, while Near stays claimed
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::Bundle {
+            rewards: vec![
+                JsonPool {
+                    pool_id: 0,
+                    sponsor_id: None,
+                    vesting: None,
+                    rewards: JsonPoolRewards::NonFungibleToken {
+                        contract_id: nft(),
+                        token_ids: vec!["token_1".to_string(), "token_2".to_string()],
+                        token_weights: Default::default(),
+                        total: 2,
+                    },
+                },
+                JsonPool {
+                    pool_id: 1,
+                    sponsor_id: None,
+                    vesting: None,
+                    rewards: JsonPoolRewards::Near {
+                        amount: U128(ONE_NEAR),
+                        available: 2,
+                        total: 3,
+                    },
+                },
+            ],
+            available: 2,
+        }
+    );
+}
+
+#[test]
+fn test_transfer_bundle_reward_callback_undoes_claim_when_all_parts_failed() {
     let (mut contract, mut context, quest) = setup(Some(10), None, None);
 
-    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    add_bundle_of_near_and_nft(&mut contract, &mut context);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    let reward = claimed_reward(&contract, quest.id, box_id);
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed, PromiseResult::Failed]
+    );
+
+    assert!(contract
+        .transfer_reward_callback(quest.id, user1(), box_id, 2, reward)
+        .is_none());
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert_eq!(boxes.first().unwrap().box_status, JsonBoxStatus::NonClaimed);
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    match rewards.first().unwrap().rewards.to_owned() {
+        JsonPoolRewards::Bundle { available, .. } => assert_eq!(available, 2),
+        _ => panic!("Pool should be a bundle"),
+    }
+}
+
+#[test]
+fn test_withdraw_nft_pool_tokens_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_nft_tokens(
+        &mut contract,
+        &mut context,
+        vec!["token_1", "token_2", "token_3"],
+    );
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_nft_pool(quest.id, 0, Some(vec!["token_2".to_string()]));
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::NonFungibleToken {
+            contract_id: nft(),
+            token_ids: vec!["token_1".to_string(), "token_3".to_string()],
+            token_weights: Default::default(),
+            total: 2,
+        }
+    );
+}
+
+#[test]
+fn test_withdraw_all_nft_pool_tokens_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_nft_tokens(&mut contract, &mut context, vec!["token_1", "token_2"]);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_nft_pool(quest.id, 0, None);
+
+    assert_eq!(
+        contract
+            .available_rewards(quest.id, BoxRarity::Rare, None)
+            .len(),
+        0
+    );
+}
+
+#[test]
+#[should_panic(expected = "Token token_3 isn't available in the pool")]
+fn test_withdraw_unknown_nft_pool_token_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_nft_tokens(&mut contract, &mut context, vec!["token_1", "token_2"]);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_nft_pool(quest.id, 0, Some(vec!["token_3".to_string()]));
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_withdraw_nft_pool_by_regular_user_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_nft_tokens(&mut contract, &mut context, vec!["token_1"]);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.withdraw_nft_pool(quest.id, 0, None);
+}
+
+#[test]
+fn test_withdraw_nft_token_callback_failure_returns_token() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_nft_tokens(&mut contract, &mut context, vec!["token_1", "token_2"]);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_nft_pool(quest.id, 0, Some(vec!["token_1".to_string()]));
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed]
+    );
+
+    assert!(!contract.withdraw_nft_token_callback(quest.id, owner(), 0, "token_1".to_string()));
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::NonFungibleToken {
+            contract_id: nft(),
+            token_ids: vec!["token_1".to_string(), "token_2".to_string()],
+            token_weights: Default::default(),
+            total: 2,
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_TOO_MANY_RESULTS")]
+fn test_withdraw_nft_token_callback_by_someone_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.withdraw_nft_token_callback(quest.id, owner(), 0, "token_1".to_string());
+}
+
+#[test]
+fn test_add_nft_pool_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.trust_nft_contract(nft().clone());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+}
+
+#[test]
+fn test_add_multiple_nft_pool_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_nft_contract(nft().clone());
+    contract.trust_nft_contract(nft2().clone());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+    contract.nft_on_transfer(
+        owner(),
+        owner(),
+        "some_token_2".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_NFT_CONTRACT_NOT_TRUSTED")]
+fn test_add_non_whitelisted_nft_pool_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, Some(nft3()));
+
+    contract.nft_on_transfer(
+        owner(),
+        owner(),
+        "some_token".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+}
+
+#[test]
+fn test_add_ft_pool_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_ft_contract(ft());
+
+    testing_env!(context.predecessor_account_id(ft()).build());
+
+    let result = contract.ft_on_transfer(
+        owner(),
+        U128(1_000),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare",
+            "amount": "300",
+            "capacity": "3"
+        }
+        "#,
+        ),
+    );
+
+    // unused tokens are returned back to the sender
+    match result {
+        PromiseOrValue::Value(unused) => assert_eq!(unused, U128(100)),
+        PromiseOrValue::Promise(_) => panic!("Expected value"),
+    }
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards,
+        vec![JsonPool {
+            pool_id: 0,
+            sponsor_id: None,
+            vesting: None,
+            rewards: JsonPoolRewards::FungibleToken {
+                contract_id: ft(),
+                amount: U128(300),
+                available: 3,
+                total: 3,
+            },
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_FT_CONTRACT_NOT_TRUSTED")]
+fn test_add_non_whitelisted_ft_pool_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, Some(ft()));
+
+    contract.ft_on_transfer(
+        owner(),
+        U128(1_000),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare",
+            "amount": "100",
+            "capacity": "10"
+        }
+        "#,
+        ),
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_add_ft_pool_by_regular_user_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_ft_contract(ft());
+
+    testing_env!(context.predecessor_account_id(ft()).build());
+
+    contract.ft_on_transfer(
+        user1(),
+        U128(1_000),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare",
+            "amount": "100",
+            "capacity": "10"
+        }
+        "#,
+        ),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Transferred amount must be at least 1000 tokens")]
+fn test_add_ft_pool_with_insufficient_amount_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_ft_contract(ft());
+
+    testing_env!(context.predecessor_account_id(ft()).build());
+
+    contract.ft_on_transfer(
+        owner(),
+        U128(999),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare",
+            "amount": "100",
+            "capacity": "10"
+        }
+        "#,
+        ),
+    );
+}
+
+fn add_mt_tokens(contract: &mut Contract, context: &mut VMContextBuilder) -> Vec<U128> {
+    contract.trust_mt_contract(mt());
+
+    testing_env!(context.predecessor_account_id(mt()).build());
+
+    let result = contract.mt_on_transfer(
+        owner(),
+        vec![owner(), owner()],
+        vec!["gold".to_string(), "silver".to_string()],
+        vec![U128(250), U128(100)],
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare",
+            "amount": "100"
+        }
+        "#,
+        ),
+    );
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+
+    match result {
+        PromiseOrValue::Value(unused) => unused,
+        PromiseOrValue::Promise(_) => panic!("Expected value"),
+    }
+}
+
+#[test]
+fn test_add_mt_pool_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let unused = add_mt_tokens(&mut contract, &mut context);
+
+    // unused tokens are returned back per token id
+    assert_eq!(unused, vec![U128(50), U128(0)]);
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards,
+        vec![JsonPool {
+            pool_id: 0,
+            sponsor_id: None,
+            vesting: None,
+            rewards: JsonPoolRewards::MultiToken {
+                contract_id: mt(),
+                amount: U128(100),
+                balances: [
+                    ("gold".to_string(), U128(200)),
+                    ("silver".to_string(), U128(100)),
+                ]
+                .into_iter()
+                .collect(),
+                available: 3,
+                total: 3,
+            },
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_MT_CONTRACT_NOT_TRUSTED")]
+fn test_add_non_whitelisted_mt_pool_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, Some(mt()));
+
+    contract.mt_on_transfer(
+        owner(),
+        vec![owner()],
+        vec!["gold".to_string()],
+        vec![U128(100)],
+        String::from(r#"{ "quest_id": 0, "rarity": "rare", "amount": "100" }"#),
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_add_mt_pool_by_regular_user_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_mt_contract(mt());
+
+    testing_env!(context.predecessor_account_id(mt()).build());
+
+    contract.mt_on_transfer(
+        user1(),
+        vec![user1()],
+        vec!["gold".to_string()],
+        vec![U128(100)],
+        String::from(r#"{ "quest_id": 0, "rarity": "rare", "amount": "100" }"#),
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_NOT_ENOUGH_TOKENS")]
+fn test_add_mt_pool_with_insufficient_amount_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_mt_contract(mt());
+
+    testing_env!(context.predecessor_account_id(mt()).build());
+
+    contract.mt_on_transfer(
+        owner(),
+        vec![owner()],
+        vec!["gold".to_string()],
+        vec![U128(99)],
+        String::from(r#"{ "quest_id": 0, "rarity": "rare", "amount": "100" }"#),
+    );
+}
+
+#[test]
+fn test_claim_mt_reward_and_undo_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_mt_tokens(&mut contract, &mut context);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    let reward = claimed_reward(&contract, quest.id, box_id);
+
+    assert_eq!(
+        JsonReward::from(reward.clone()),
+        JsonReward::MultiToken {
+            contract_id: mt(),
+            token_id: "gold".to_string(),
+            amount: U128(100),
+        }
+    );
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed],
+    );
+
+    contract.transfer_reward_callback(quest.id, user1(), box_id, 0, reward);
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    match &rewards.first().unwrap().rewards {
+        JsonPoolRewards::MultiToken {
+            available,
+            balances,
+            ..
+        } => {
+            assert_eq!(*available, 3);
+            assert_eq!(balances.get("gold"), Some(&U128(200)));
+        }
+        _ => panic!("Expected multi token pool"),
+    }
+}
+
+#[test]
+fn test_pool_odds_default_to_availability() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(3), None);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(1), None);
+
+    let odds = contract.pool_odds(quest.id, BoxRarity::Rare);
+
+    assert_eq!(
+        odds,
+        vec![
+            JsonPoolOdds {
+                pool_id: 0,
+                weight: U64(3),
+                total_weight: U128(4),
+            },
+            JsonPoolOdds {
+                pool_id: 1,
+                weight: U64(1),
+                total_weight: U128(4),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_set_pool_weight_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(3), None);
+    contract.add_near_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR),
+        U64(1),
+        Some(U64(7)),
+    );
+
+    contract.set_pool_weight(quest.id, 0, Some(U64(1)));
+
+    let odds = contract.pool_odds(quest.id, BoxRarity::Rare);
+
+    assert_eq!(odds.first().unwrap().weight, U64(1));
+    assert_eq!(odds.get(1).unwrap().weight, U64(7));
+    assert_eq!(odds.first().unwrap().total_weight, U128(8));
+
+    // resetting the weight brings availability back
+    contract.set_pool_weight(quest.id, 1, None);
+
+    let odds = contract.pool_odds(quest.id, BoxRarity::Rare);
+
+    assert_eq!(odds.get(1).unwrap().weight, U64(1));
+}
+
+#[test]
+#[should_panic(expected = "ERR_ZERO_WEIGHT")]
+fn test_set_zero_pool_weight_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(3), None);
+
+    contract.set_pool_weight(quest.id, 0, Some(U64(0)));
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_set_pool_weight_by_regular_user_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(3), None);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.set_pool_weight(quest.id, 0, Some(U64(5)));
+}
+
+#[test]
+fn test_add_nft_pool_with_weight_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_nft_contract(nft());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        owner(),
+        owner(),
+        "1".to_string(),
+        String::from(r#"{ "quest_id": 0, "rarity": "rare", "weight": "50" }"#),
+    );
+
+    let odds = contract.pool_odds(quest.id, BoxRarity::Rare);
+
+    assert_eq!(odds.first().unwrap().weight, U64(50));
+}
+
+#[test]
+fn test_claim_picks_pool_by_weight() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
+    contract.add_near_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR / 2),
+        U64(1),
+        Some(U64(1_000_000_000)),
+    );
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    // the default seed is all zeros, which always picks the first listed pool
+    testing_env!(context
+        .random_seed([u8::MAX; 32])
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    assert_eq!(
+        claimed_reward(&contract, quest.id, box_id),
+        Reward::Near {
+            amount: ONE_NEAR / 2
+        }
+    );
+
+    // the weighted pool is empty now, so it's skipped regardless of its weight
+    let odds = contract.pool_odds(quest.id, BoxRarity::Rare);
+
+    assert_eq!(
+        odds,
+        vec![JsonPoolOdds {
+            pool_id: 0,
+            weight: U64(5),
+            total_weight: U128(5),
+        }]
+    );
+}
+
+fn add_weighted_nft_tokens(contract: &mut Contract, context: &mut VMContextBuilder) {
+    contract.trust_nft_contract(nft());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        owner(),
+        owner(),
+        "1".to_string(),
+        String::from(r#"{ "quest_id": 0, "rarity": "rare" }"#),
+    );
+    contract.nft_on_transfer(
+        owner(),
+        owner(),
+        "2".to_string(),
+        String::from(r#"{ "quest_id": 0, "rarity": "rare", "token_weight": "1000000000" }"#),
+    );
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+}
+
+#[test]
+fn test_add_nft_token_with_weight_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_weighted_nft_tokens(&mut contract, &mut context);
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards.first().unwrap().rewards,
+        JsonPoolRewards::NonFungibleToken {
+            contract_id: nft(),
+            token_ids: vec!["1".to_string(), "2".to_string()],
+            token_weights: [("2".to_string(), U64(1_000_000_000))]
+                .into_iter()
+                .collect(),
+            total: 2,
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_ZERO_WEIGHT")]
+fn test_add_nft_token_with_zero_weight_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_nft_contract(nft());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        owner(),
+        owner(),
+        "1".to_string(),
+        String::from(r#"{ "quest_id": 0, "rarity": "rare", "token_weight": "0" }"#),
+    );
+}
+
+#[test]
+fn test_claim_picks_nft_token_by_weight() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_weighted_nft_tokens(&mut contract, &mut context);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .random_seed([u8::MAX; 32])
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    // the smallest token id isn't taken first anymore
+    assert_eq!(
+        claimed_reward(&contract, quest.id, box_id),
+        Reward::NonFungibleToken {
+            contract_id: nft(),
+            token_id: "2".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_returned_nft_token_keeps_weight() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_weighted_nft_tokens(&mut contract, &mut context);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_nft_pool(quest.id, 0, Some(vec!["2".to_string()]));
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed],
+    );
+
+    contract.withdraw_nft_token_callback(quest.id, owner(), 0, "2".to_string());
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    match &rewards.first().unwrap().rewards {
+        JsonPoolRewards::NonFungibleToken { token_weights, .. } => {
+            assert_eq!(token_weights.get("2"), Some(&U64(1_000_000_000)));
+        }
+        _ => panic!("Expected NFT pool"),
+    }
+}
+
+#[test]
+fn test_top_up_near_pool_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    testing_env!(context.attached_deposit(3 * ONE_NEAR).build());
+
+    contract.top_up_near_pool(quest.id, 0, U64(3));
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    // the pool is extended instead of adding a new one
+    assert_eq!(
+        rewards,
+        vec![JsonPool {
+            pool_id: 0,
+            sponsor_id: None,
+            vesting: None,
+            rewards: JsonPoolRewards::Near {
+                amount: U128(ONE_NEAR),
+                available: 5,
+                total: 5,
+            },
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be equal to 3000000000000000000000000 yocto")]
+fn test_top_up_near_pool_with_insufficient_deposit_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    testing_env!(context.attached_deposit(2 * ONE_NEAR).build());
+
+    contract.top_up_near_pool(quest.id, 0, U64(3));
+}
+
+#[test]
+#[should_panic(expected = "ERR_NOT_NEAR_POOL")]
+fn test_top_up_nft_pool_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_nft_tokens(&mut contract, &mut context, vec!["1"]);
+
+    contract.top_up_near_pool(quest.id, 0, U64(3));
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_top_up_near_pool_by_regular_user_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    testing_env!(context
+        .attached_deposit(3 * ONE_NEAR)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.top_up_near_pool(quest.id, 0, U64(3));
+}
+
+fn add_sponsored_near_reward(contract: &mut Contract, context: &mut VMContextBuilder) {
+    contract.add_sponsor(0, user2());
+
+    testing_env!(context
+        .attached_deposit(5 * ONE_NEAR)
+        .predecessor_account_id(user2())
+        .build());
+
+    contract.add_near_reward(0, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+}
+
+#[test]
+fn test_add_sponsor_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_sponsor(quest.id, user2());
+
+    assert_eq!(contract.get_sponsors(quest.id), vec![user2()]);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.remove_sponsor(quest.id, user2());
+
+    assert_eq!(contract.get_sponsors(quest.id), vec![]);
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_add_sponsor_by_regular_user_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.add_sponsor(quest.id, user2());
+}
+
+#[test]
+fn test_sponsor_adds_near_reward_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_sponsored_near_reward(&mut contract, &mut context);
+
+    let rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+
+    assert_eq!(
+        rewards,
+        vec![JsonPool {
+            pool_id: 0,
+            sponsor_id: Some(user2()),
+            vesting: None,
+            rewards: JsonPoolRewards::Near {
+                amount: U128(ONE_NEAR),
+                available: 2,
+                total: 2,
+            },
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_add_near_reward_by_non_sponsor_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    testing_env!(context.predecessor_account_id(user2()).build());
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+}
+
+#[test]
+fn test_sponsor_nft_tokens_are_kept_apart() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_sponsor(quest.id, user2());
+
+    add_nft_tokens(&mut contract, &mut context, vec!["1"]);
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        user2(),
+        user2(),
+        "2".to_string(),
+        String::from(r#"{ "quest_id": 0, "rarity": "rare" }"#),
+    );
+
+    let mut rewards = contract.rewards(quest.id, BoxRarity::Rare, None);
+    rewards.sort_by_key(|pool| pool.pool_id);
+
+    assert_eq!(rewards.len(), 2);
+    assert_eq!(rewards.first().unwrap().sponsor_id, None);
+    assert_eq!(rewards.get(1).unwrap().sponsor_id, Some(user2()));
+}
+
+#[test]
+#[should_panic(expected = "ERR_SPONSORED_POOL")]
+fn test_withdraw_sponsored_pool_by_owner_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_sponsored_near_reward(&mut contract, &mut context);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_near_pool(quest.id, 0, U64(1));
+}
+
+#[test]
+#[should_panic(expected = "ERR_QUEST_NOT_CLOSED")]
+fn test_refund_sponsored_pool_before_closing_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_sponsored_near_reward(&mut contract, &mut context);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user2())
+        .build());
+
+    contract.refund_sponsored_pool(quest.id, 0);
+}
+
+#[test]
+fn test_refund_sponsored_pool_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_sponsored_near_reward(&mut contract, &mut context);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+
+    contract.close_quest(quest.id);
+
+    testing_env!(context.predecessor_account_id(user2()).build());
+
+    contract.refund_sponsored_pool(quest.id, 0);
+
+    let logs = get_logs();
+    let event = logs.last().unwrap();
+
+    // the claimed reward isn't refunded
+    assert!(event.contains(r#""event":"sponsored_pool_refund""#));
+    assert!(event.contains(&format!(r#""amount":"{}""#, ONE_NEAR)));
+
+    assert_eq!(contract.rewards(quest.id, BoxRarity::Rare, None), vec![]);
+}
+
+#[test]
+fn test_refund_sponsored_pool_keeps_claims_in_flight() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_sponsored_near_reward(&mut contract, &mut context);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    let reward = claimed_reward(&contract, quest.id, box_id);
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+
+    contract.close_quest(quest.id);
+
+    testing_env!(context.predecessor_account_id(user2()).build());
+
+    contract.refund_sponsored_pool(quest.id, 0);
+
+    // the reward transfer of the claim fails after the refund
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed]
+    );
+
+    contract.transfer_reward_callback(quest.id, user1(), box_id, 0, reward);
+
+    testing_env!(context.predecessor_account_id(user2()).build());
+
+    contract.refund_sponsored_pool(quest.id, 0);
+
+    let logs = get_logs();
+    let event = logs.last().unwrap();
+
+    assert!(event.contains(&format!(r#""amount":"{}""#, ONE_NEAR)));
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_refund_sponsored_pool_by_someone_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_sponsored_near_reward(&mut contract, &mut context);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.close_quest(quest.id);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.refund_sponsored_pool(quest.id, 0);
+}
+
+#[test]
+fn test_claim_in_closed_quest_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.close_quest(quest.id);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.claim(quest.id, box_id);
+
+    assert_eq!(
+        claimed_reward(&contract, quest.id, box_id),
+        Reward::Near { amount: ONE_NEAR }
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_QUEST_CLOSED")]
+fn test_mint_in_closed_quest_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.close_quest(quest.id);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+}
+
+#[test]
+fn test_add_near_reward_takes_fee() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    // 2.5%
+    contract.set_fee(250);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(4), None);
+
+    assert_eq!(
+        contract.get_quest_fees(quest.id),
+        JsonQuestFees {
+            quest_id: quest.id,
+            fee_bps: 250,
+            collected: U128(ONE_NEAR / 10),
+        }
+    );
+    assert_eq!(contract.get_fees().pending, U128(ONE_NEAR / 10));
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be equal to")]
+fn test_add_near_reward_without_fee_deposit_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.set_fee(250);
+
+    testing_env!(context.attached_deposit(4 * ONE_NEAR).build());
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(4), None);
+}
+
+#[test]
+fn test_quest_fee_override_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.set_fee(250);
+    contract.set_quest_fee(quest.id, Some(0));
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(4), None);
+
+    assert_eq!(
+        contract.get_quest_fees(quest.id),
+        JsonQuestFees {
+            quest_id: quest.id,
+            fee_bps: 0,
+            collected: U128(0),
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_set_fee_by_regular_user_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.set_fee(250);
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_FEE")]
+fn test_set_fee_above_maximum_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_fee(10_001);
+}
+
+#[test]
+fn test_withdraw_fees_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.set_fee(250);
+    contract.set_fee_treasury(user3());
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(4), None);
+
+    testing_env!(context.predecessor_account_id(user3()).build());
+
+    contract.withdraw_fees();
+
+    assert_eq!(contract.get_fees().pending, U128(0));
+    // collected fees are kept in the history
+    assert_eq!(
+        contract.get_quest_fees(quest.id).collected,
+        U128(ONE_NEAR / 10)
+    );
+}
+
+#[test]
+fn test_withdraw_fees_restores_pending_on_failure() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.set_fee(250);
+    contract.set_fee_treasury(user3());
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(4), None);
+
+    testing_env!(context.predecessor_account_id(user3()).build());
+
+    contract.withdraw_fees();
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed],
+    );
+
+    assert!(!contract.withdraw_fees_callback(user3(), U128(ONE_NEAR / 10)));
+    assert_eq!(contract.get_fees().pending, U128(ONE_NEAR / 10));
+}
+
+#[test]
+#[should_panic(expected = "ERR_NO_FEES_TO_WITHDRAW")]
+fn test_withdraw_fees_without_fees_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.withdraw_fees();
+}
+
+fn claim_in_escrow(contract: &mut Contract, context: &mut VMContextBuilder, quest_id: u64) -> u128 {
+    contract.set_escrow_mode(quest_id, true);
+
+    let box_id = contract.mint(quest_id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(ONE_NEAR / 100)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest_id, box_id);
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Successful(vec![])],
+    );
+
+    contract.check_iah_verification_and_claim_callback(quest_id, user1(), box_id, 0, true);
+
+    box_id
+}
+
+#[test]
+fn test_claim_in_escrow_mode_locks_reward() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    let box_id = claim_in_escrow(&mut contract, &mut context, quest.id);
+
+    assert_eq!(
+        contract.pending_rewards(user1()),
+        vec![JsonPendingReward {
+            quest_id: quest.id,
+            box_id,
+            reward: JsonReward::Near {
+                amount: U128(ONE_NEAR),
+            },
+        }]
+    );
+}
+
+#[test]
+fn test_withdraw_rewards_keeps_failed_ones() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    let box_id = claim_in_escrow(&mut contract, &mut context, quest.id);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.withdraw_rewards();
+
+    assert_eq!(contract.pending_rewards(user1()), vec![]);
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed],
+    );
+
+    let pending_reward = PendingReward {
+        quest_id: quest.id,
+        box_id,
+        reward: claimed_reward(&contract, quest.id, box_id),
+    };
+
+    assert!(!contract.withdraw_pending_reward_callback(user1(), pending_reward));
+
+    // the box isn't reset, so the reward can't be re-rolled
+    assert_eq!(contract.pending_rewards(user1()).len(), 1);
+    assert_eq!(
+        claimed_reward(&contract, quest.id, box_id),
+        Reward::Near { amount: ONE_NEAR }
+    );
+}
+
+#[test]
+fn test_withdraw_rewards_keeps_failed_bundle_parts() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    add_bundle_of_near_and_nft(&mut contract, &mut context);
+
+    let box_id = claim_in_escrow(&mut contract, &mut context, quest.id);
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Successful(vec![]), PromiseResult::Failed],
+    );
+
+    let reward = claimed_reward(&contract, quest.id, box_id);
+
+    contract.withdraw_pending_reward_callback(
+        user1(),
+        PendingReward {
+            quest_id: quest.id,
+            box_id,
+            reward: reward.clone(),
+        },
+    );
+
+    let pending_rewards = contract.pending_rewards(user1());
+
+    assert_eq!(
+        pending_rewards.last().unwrap().reward,
+        JsonReward::from(reward.parts(&[1]))
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_NO_REWARDS_TO_WITHDRAW")]
+fn test_withdraw_rewards_without_rewards_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_rewards();
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be bigger than")]
+fn test_claim_in_escrow_mode_without_storage_deposit_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.set_escrow_mode(quest.id, true);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_set_escrow_mode_by_regular_user_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.set_escrow_mode(quest.id, true);
+}
+
+const DAY: u64 = 86_400 * 1_000_000_000;
+
+fn claim_vested_near_reward(
+    contract: &mut Contract,
+    context: &mut VMContextBuilder,
+    quest_id: u64,
+    schedule: VestingSchedule,
+) -> u128 {
+    contract.add_near_reward(quest_id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+    contract.set_pool_vesting(quest_id, 0, Some(schedule));
+
+    let box_id = contract.mint(quest_id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(ONE_NEAR / 100)
+        .predecessor_account_id(user1())
+        .block_timestamp(0)
+        .build());
+
+    contract.claim(quest_id, box_id);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    box_id
+}
+
+#[test]
+fn test_claim_vested_reward_records_position() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    let schedule = VestingSchedule {
+        cliff: U64(0),
+        duration: U64(30 * DAY),
+    };
+
+    let box_id = claim_vested_near_reward(&mut contract, &mut context, quest.id, schedule.clone());
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Successful(vec![])],
+    );
+
+    // no transfer is made for the vested reward
+    match contract.check_iah_verification_and_claim_callback(quest.id, user1(), box_id, 0, false) {
+        PromiseOrValue::Value(result) => assert!(result.is_some()),
+        PromiseOrValue::Promise(_) => panic!("Expected value"),
+    }
+
+    assert_eq!(
+        contract.vesting_positions(quest.id, user1()),
+        vec![JsonVestingPosition {
+            box_id,
+            amount: U128(ONE_NEAR),
+            withdrawn: U128(0),
+            withdrawable: U128(0),
+            start: U64(0),
+            schedule,
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be bigger than")]
+fn test_claim_vested_reward_without_storage_deposit_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+    contract.set_pool_vesting(
+        quest.id,
+        0,
+        Some(VestingSchedule {
+            cliff: U64(0),
+            duration: U64(30 * DAY),
+        }),
+    );
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+}
+
+#[test]
+fn test_withdraw_vested_releases_linearly() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    claim_vested_near_reward(
+        &mut contract,
+        &mut context,
+        quest.id,
+        VestingSchedule {
+            cliff: U64(0),
+            duration: U64(30 * DAY),
+        },
+    );
+
+    testing_env!(context.block_timestamp(15 * DAY).build());
+
+    contract.withdraw_vested(quest.id);
+
+    let positions = contract.vesting_positions(quest.id, user1());
+
+    assert_eq!(positions.first().unwrap().withdrawn, U128(ONE_NEAR / 2));
+    assert_eq!(positions.first().unwrap().withdrawable, U128(0));
+
+    testing_env!(
+        context.block_timestamp(30 * DAY).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Successful(vec![])],
+    );
+
+    contract.withdraw_vested(quest.id);
+    contract.withdraw_vested_callback(quest.id, user1(), vec![(0, U128(ONE_NEAR / 2))]);
+
+    // fully withdrawn positions are removed
+    assert_eq!(contract.vesting_positions(quest.id, user1()), vec![]);
+}
+
+#[test]
+#[should_panic(expected = "ERR_NOTHING_VESTED")]
+fn test_withdraw_vested_before_cliff_with_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    claim_vested_near_reward(
+        &mut contract,
+        &mut context,
+        quest.id,
+        VestingSchedule {
+            cliff: U64(7 * DAY),
+            duration: U64(7 * DAY),
+        },
+    );
+
+    testing_env!(context.block_timestamp(7 * DAY - 1).build());
+
+    contract.withdraw_vested(quest.id);
+}
+
+#[test]
+fn test_failed_vested_withdrawal_is_reverted() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    claim_vested_near_reward(
+        &mut contract,
+        &mut context,
+        quest.id,
+        VestingSchedule {
+            cliff: U64(7 * DAY),
+            duration: U64(7 * DAY),
+        },
+    );
+
+    testing_env!(
+        context.block_timestamp(7 * DAY).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed],
+    );
+
+    contract.withdraw_vested(quest.id);
+    contract.withdraw_vested_callback(quest.id, user1(), vec![(0, U128(ONE_NEAR))]);
+
+    let positions = contract.vesting_positions(quest.id, user1());
+
+    assert_eq!(positions.first().unwrap().withdrawable, U128(ONE_NEAR));
+}
+
+#[test]
+#[should_panic(expected = "ERR_VESTING_NOT_SUPPORTED")]
+fn test_set_nft_pool_vesting_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_nft_tokens(&mut contract, &mut context, vec!["1"]);
+
+    contract.set_pool_vesting(
+        quest.id,
+        0,
+        Some(VestingSchedule {
+            cliff: U64(0),
+            duration: U64(DAY),
+        }),
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_CLIFF_LONGER_THAN_DURATION")]
+fn test_set_pool_vesting_with_long_cliff_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    contract.set_pool_vesting(
+        quest.id,
+        0,
+        Some(VestingSchedule {
+            cliff: U64(2 * DAY),
+            duration: U64(DAY),
+        }),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Bundle can't contain a vested pool")]
+fn test_add_bundle_reward_with_vested_pool_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    contract.set_pool_vesting(
+        quest.id,
+        0,
+        Some(VestingSchedule {
+            cliff: U64(DAY),
+            duration: U64(2 * DAY),
+        }),
+    );
+
+    contract.add_bundle_reward(quest.id, BoxRarity::Rare, vec![0, 1], None);
+}
+
+#[test]
+#[should_panic(expected = "Vesting can't be set on a bundled pool")]
+fn test_set_pool_vesting_on_bundled_pool_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
+
+    contract.add_bundle_reward(quest.id, BoxRarity::Rare, vec![0, 1], None);
+
+    contract.set_pool_vesting(
+        quest.id,
+        1,
+        Some(VestingSchedule {
+            cliff: U64(DAY),
+            duration: U64(2 * DAY),
+        }),
+    );
+}
+
+#[test]
+fn test_available_near_rewards_amount() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
+
+    let rewards = quest.available_rewards(BoxRarity::Rare, None);
+
+    assert_eq!(rewards.len(), 1);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
+
+    let rewards = quest.available_rewards(BoxRarity::Rare, None);
+
+    assert_eq!(rewards.len(), 2);
+
+    contract.add_near_reward(quest.id, BoxRarity::Epic, U128(ONE_NEAR), U64(5), None);
+
+    let rewards = quest.available_rewards(BoxRarity::Rare, None);
+    assert_eq!(rewards.len(), 2);
+
+    let rewards = quest.available_rewards(BoxRarity::Epic, None);
+    assert_eq!(rewards.len(), 1);
+}
+
+#[test]
+fn test_available_near_rewards_data() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
+
+    let rewards = quest.available_rewards(BoxRarity::Rare, None);
+
+    let reward = rewards.first().unwrap().rewards.to_owned();
+
+    assert_eq!(
+        reward,
+        JsonPoolRewards::Near {
+            amount: U128(ONE_NEAR),
+            available: 5,
+            total: 5,
+        }
+    );
+}
+
+#[test]
+fn test_available_nft_rewards_amount_for_different_contracts() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_nft_contract(nft().clone());
+    contract.trust_nft_contract(nft2().clone());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+
+    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
+    assert_eq!(rewards.len(), 1);
+
+    testing_env!(context.predecessor_account_id(nft2()).build());
+    contract.nft_on_transfer(
+        nft2(),
+        owner(),
+        "another_token".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+
+    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
+    assert_eq!(rewards.len(), 2);
+}
+
+#[test]
+fn test_available_nft_rewards_amount_for_same_contract() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_nft_contract(nft().clone());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+
+    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
+    assert_eq!(rewards.len(), 1);
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token_2".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+
+    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
+    assert_eq!(rewards.len(), 1);
+}
+
+#[test]
+fn test_available_nft_rewards_amount_for_same_contract_and_different_rarity() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_nft_contract(nft().clone());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare"
+        }
+        "#,
+        ),
+    );
+
+    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
+    assert_eq!(rewards.len(), 1);
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token_2".to_string(),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "epic"
+        }
+        "#,
+        ),
+    );
+
+    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
+    assert_eq!(rewards.len(), 1);
+    let rewards = contract.available_rewards(quest.id, BoxRarity::Epic, None);
+    assert_eq!(rewards.len(), 1);
+}
+
+#[test]
+fn test_available_nft_rewards_data_for_same_contract() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_nft_contract(nft().clone());
+
+    testing_env!(context.predecessor_account_id(nft()).build());
+
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token".to_string(),
+        format!(
+            r#"
+        {{
+            "quest_id": {},
+            "rarity": "rare"
+        }}
+        "#,
+            quest.id
+        ),
+    );
+    contract.nft_on_transfer(
+        nft(),
+        owner(),
+        "some_token_2".to_string(),
+        format!(
+            r#"
+        {{
+            "quest_id": {},
+            "rarity": "rare"
+        }}
+        "#,
+            quest.id
+        ),
+    );
+
+    let rewards = contract.available_rewards(quest.id, BoxRarity::Rare, None);
+
+    let reward = rewards.first().unwrap().rewards.to_owned();
+
+    assert_eq!(
+        reward,
+        JsonPoolRewards::NonFungibleToken {
+            contract_id: nft(),
+            token_ids: vec!["some_token".to_string(), "some_token_2".to_string()],
+            token_weights: Default::default(),
+            total: 2,
+        }
+    );
+}
+
+#[test]
+fn test_mint_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+}
+
+#[test]
+fn test_delete_boxes_succeeds() {
+    let (mut contract, mut context, _) = setup(None, None, Some(user1()));
+
+    let mut quest = create_quest(&mut contract, &mut context, Some(10));
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Epic);
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(2));
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.delete_boxes(quest.id, vec![0]);
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(1));
+
+    assert!(quest.boxes.get(&box_id).is_none());
+}
+
+#[test]
+fn test_delete_boxes_cleans_up_holder() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    contract.mint_many(quest.id, BoxRarity::Epic, vec![user2(), user2()]);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.delete_boxes(quest.id, vec![1]);
+
+    assert_eq!(
+        contract.questboxes_supply_per_owner(user2(), quest.id),
+        U128(1)
+    );
+    assert_eq!(contract.get_users(quest.id, None), vec![user1(), user2()]);
+
+    contract.delete_boxes(quest.id, vec![0, 2]);
+
+    assert_eq!(
+        contract.questboxes_supply_per_owner(user1(), quest.id),
+        U128(0)
+    );
+    assert_eq!(
+        contract.questboxes_supply_per_owner(user2(), quest.id),
+        U128(0)
+    );
+    assert_eq!(contract.get_users(quest.id, None).len(), 0);
+    assert_eq!(contract.questboxes_total_supply(quest.id), U128(0));
+    assert_eq!(contract.nft_total_supply(), U128(0));
+
+    assert!(get_logs().last().unwrap().contains(r#""event":"nft_burn""#));
+}
+
+#[test]
+#[should_panic(expected = "Box 0 already claimed")]
+fn test_delete_claimed_box_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1), None);
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+
+    contract.delete_boxes(quest.id, vec![box_id]);
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_delete_boxes_by_non_owner_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.delete_boxes(quest.id, vec![box_id]);
+}
+
+#[test]
+fn test_mint_many_succeeds() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user2()]);
+}
+
+#[test]
+#[should_panic(expected = "Accounts can't be empty")]
+fn test_mint_many_without_accounts_panic() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![]);
+}
+
+#[test]
+fn test_total_supply_default() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    assert_eq!(contract.questboxes_total_supply(quest.id), U128(0));
+}
+
+#[test]
+fn test_total_supply_increases() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    assert_eq!(contract.questboxes_total_supply(quest.id), U128(1));
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    contract.mint(quest.id, user2(), BoxRarity::Epic);
+
+    assert_eq!(contract.questboxes_total_supply(quest.id), U128(3));
+
+    contract.mint_many(quest.id, BoxRarity::Legendary, vec![user1(), user2()]);
+
+    assert_eq!(contract.questboxes_total_supply(quest.id), U128(5));
+}
+
+#[test]
+fn test_supply_for_owner_default() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(0));
+}
+
+#[test]
+fn test_supply_for_owner_increases() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(1));
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    contract.mint(quest.id, user2(), BoxRarity::Epic);
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(2));
+    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(1));
+
+    contract.mint_many(
+        quest.id,
+        BoxRarity::Legendary,
+        vec![user1(), user2(), user1()],
+    );
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(4));
+    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(2));
+}
+
+#[test]
+fn test_quest_boxes_differ_after_mint_many() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(0));
+    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(0));
+
+    contract.mint_many(
+        quest.id,
+        BoxRarity::Rare,
+        vec![user1(), user2()],
+    );
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(1));
+    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(1));
+
+    contract.mint_many(
+        quest.id,
+        BoxRarity::Rare,
+        vec![user1(), user2()],
+    );
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(2));
+    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(2));
+
+    let pagination = Some(Pagination::new(1, 40));
+
+    let user_1_quest_boxes = contract.questboxes_per_owner(user1(), quest.id, pagination.clone());
+    let user_2_quest_boxes = contract.questboxes_per_owner(user2(), quest.id, pagination.clone());
+
+    let user_1_quest_box = user_1_quest_boxes.get(1).unwrap();
+    let user_2_quest_box = user_2_quest_boxes.get(1).unwrap();
+
+    assert_ne!(user_1_quest_box.box_id, user_2_quest_box.box_id, "Box id's should be different");
+}
+
+#[test]
+fn test_quest_per_owner_differ_per_quest() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(0));
+    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(0));
+
+    contract.mint_many(
+        quest.id,
+        BoxRarity::Rare,
+        vec![user1(), user2()],
+    );
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(1));
+    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(1));
+
+    contract.mint_many(
+        quest.id,
+        BoxRarity::Rare,
+        vec![user1(), user2()],
+    );
+
+    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(2));
+    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(2));
+
+    let pagination = Some(Pagination::new(1, 40));
+
+    let user_1_quest_boxes = contract.questboxes_per_owner(user1(), quest.id, pagination.clone());
+    let user_2_quest_boxes = contract.questboxes_per_owner(user2(), quest.id, pagination.clone());
+
+    let user_1_quest_box = user_1_quest_boxes.get(1).unwrap();
+    let user_2_quest_box = user_2_quest_boxes.get(1).unwrap();
+
+    assert_ne!(user_1_quest_box.box_id, user_2_quest_box.box_id, "Box id's should be different");
+}
+
+#[test]
+fn test_boxes_for_owner_amount() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.attached_deposit(ONE_NEAR * 2).build());
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    assert_eq!(contract.questboxes_per_owner(user1(), quest.id, None).len(), 1);
+
+    contract.mint(quest.id, user1(), BoxRarity::Epic);
+    contract.mint(quest.id, user1(), BoxRarity::Epic);
+
+    contract.mint(quest.id, user1(), BoxRarity::Legendary);
+
+    assert_eq!(contract.questboxes_per_owner(user1(), quest.id, None).len(), 4);
+
+    contract.mint(quest.id, user2(), BoxRarity::Epic);
+
+    assert_eq!(contract.questboxes_per_owner(user1(), quest.id, None).len(), 4);
+    assert_eq!(contract.questboxes_per_owner(user2(), quest.id, None).len(), 1);
+}
+
+#[test]
+fn test_boxes_for_owner_status() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    let box_data = boxes.get(0).unwrap().to_owned();
+
+    assert_eq!(box_data.box_status, JsonBoxStatus::NonClaimed);
+}
+
+#[test]
+fn test_boxes_for_quest_for_owner_amount() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let new_quest_id = contract.create_quest(&"new quest".to_string());
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    assert_eq!(
+        contract
+            .questboxes_for_quest_per_owner(quest.id, user1(), None)
+            .len(),
+        1
+    );
+
+    contract.mint(new_quest_id, user1(), BoxRarity::Epic);
+    contract.mint(new_quest_id, user1(), BoxRarity::Epic);
+
+    contract.mint(quest.id, user1(), BoxRarity::Legendary);
+
+    assert_eq!(
+        contract
+            .questboxes_for_quest_per_owner(new_quest_id, user1(), None)
+            .len(),
+        2
+    );
+    assert_eq!(
+        contract
+            .questboxes_for_quest_per_owner(quest.id, user1(), None)
+            .len(),
+        2
+    );
+}
+
+fn transfer_box_as_nft(
+    contract: &mut Contract,
+    context: &mut VMContextBuilder,
+    sender_id: AccountId,
+    receiver_id: AccountId,
+    token_id: &str,
+) {
+    testing_env!(context
+        .attached_deposit(ONE_NEAR / 100)
+        .predecessor_account_id(sender_id)
+        .build());
+
+    contract.nft_transfer(receiver_id, token_id.to_string(), None, None);
+}
+
+#[test]
+fn test_nft_token_for_box() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Legendary);
+
+    let token = contract
+        .nft_token(format!("{}:{}", quest.id, box_id))
+        .expect("Token should exist");
+
+    assert_eq!(token.token_id, format!("{}:{}", quest.id, box_id));
+    assert_eq!(token.owner_id, user1());
+
+    let metadata = token.metadata.unwrap();
+
+    assert_eq!(metadata.media, BoxRarity::Legendary.to_media_ipfs());
+    assert_eq!(metadata.title, Some(format!("{} #{}", quest.title, box_id)));
+    assert_eq!(metadata.copies, Some(1));
+}
+
+#[test]
+fn test_nft_token_for_invalid_token_id() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    assert!(contract.nft_token("0".to_string()).is_none());
+    assert!(contract.nft_token("0:box".to_string()).is_none());
+    assert!(contract.nft_token(format!("{}:5000", quest.id)).is_none());
+}
+
+#[test]
+fn test_nft_metadata() {
+    let (contract, context, quest) = setup(None, None, None);
+
+    let metadata = contract.nft_metadata();
+
+    assert_eq!(metadata.spec, "nft-1.0.0");
+    assert!(metadata.base_uri.is_some());
+}
+
+#[test]
+fn test_mint_emits_nft_mint_event() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    assert!(get_logs()
+        .last()
+        .unwrap()
+        .contains(r#""standard":"nep171","version":"1.0.0","event":"nft_mint""#));
+}
+
+#[test]
+fn test_nft_transfer_moves_box_to_receiver() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+    let token_id = format!("{}:{}", quest.id, box_id);
+
+    transfer_box_as_nft(&mut contract, &mut context, user1(), user2(), &token_id);
+
+    assert_eq!(contract.nft_token(token_id).unwrap().owner_id, user2());
+    assert_eq!(contract.nft_supply_for_owner(user1()), U128(0));
+    assert_eq!(contract.nft_supply_for_owner(user2()), U128(1));
+    assert_eq!(
+        contract.questboxes_per_owner(user2(), quest.id, None).len(),
+        1
+    );
+    assert!(contract.get_users(quest.id, None).contains(&user2()));
+
+    assert!(get_logs()
+        .last()
+        .unwrap()
+        .contains(r#""event":"nft_transfer""#));
+}
+
+#[test]
+fn test_nft_transfer_lets_receiver_claim() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1), None);
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    transfer_box_as_nft(
+        &mut contract,
+        &mut context,
+        user1(),
+        user2(),
+        &format!("{}:{}", quest.id, box_id),
+    );
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user2())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    let box_data = contract.questboxes_per_owner(user2(), quest.id, None);
+
+    assert_eq!(
+        box_data.first().unwrap().box_status,
+        JsonBoxStatus::Claimed {
+            reward: JsonReward::Near {
+                amount: U128(ONE_NEAR / 10)
+            }
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_NOT_FOUND")]
+fn test_nft_transfer_forbids_sender_claim() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1), None);
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    transfer_box_as_nft(
+        &mut contract,
+        &mut context,
+        user1(),
+        user2(),
+        &format!("{}:{}", quest.id, box_id),
+    );
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_nft_transfer_by_non_owner_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    transfer_box_as_nft(
+        &mut contract,
+        &mut context,
+        user2(),
+        user3(),
+        &format!("{}:{}", quest.id, box_id),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+fn test_nft_transfer_without_deposit_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(0)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.nft_transfer(user2(), format!("{}:{}", quest.id, box_id), None, None);
+}
+
+#[test]
+fn test_nft_enumeration() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user2(), user1()]);
+
+    let new_quest_id = contract.create_quest(&String::from("second quest"));
+
+    contract.mint(new_quest_id, user1(), BoxRarity::Epic);
+
+    assert_eq!(contract.nft_total_supply(), U128(4));
+    assert_eq!(
+        contract
+            .nft_tokens(None, None)
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect::<Vec<String>>(),
+        vec!["0:0", "0:1", "0:2", "1:0"]
+    );
+    assert_eq!(
+        contract
+            .nft_tokens(Some(U128(1)), Some(2))
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect::<Vec<String>>(),
+        vec!["0:1", "0:2"]
+    );
+
+    assert_eq!(contract.nft_supply_for_owner(user1()), U128(3));
+    assert_eq!(contract.nft_tokens_for_owner(user1(), None, None).len(), 3);
+    assert_eq!(
+        contract
+            .nft_tokens_for_owner(user1(), Some(U128(2)), Some(10))
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect::<Vec<String>>(),
+        vec!["1:0"]
+    );
+    assert_eq!(contract.nft_tokens_for_owner(user3(), None, None).len(), 0);
+}
+
+#[test]
+fn test_nft_enumeration_after_deleted_boxes() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user2(), user1()]);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.delete_boxes(quest.id, vec![0]);
+
+    assert_eq!(contract.nft_total_supply(), U128(2));
+    assert_eq!(contract.nft_tokens(Some(U128(1)), Some(10)).len(), 1);
+    assert_eq!(contract.nft_tokens(Some(U128(2)), None), vec![]);
+}
+
+#[test]
+fn test_nft_resolve_transfer_returns_box() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+    let token_id = format!("{}:{}", quest.id, box_id);
+
+    testing_env!(context
+        .attached_deposit(ONE_NEAR / 100)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.nft_transfer_call(user2(), token_id.clone(), None, None, String::new());
+
+    assert_eq!(
+        contract.nft_token(token_id.clone()).unwrap().owner_id,
+        user2()
+    );
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Successful(b"true".to_vec())],
+    );
+
+    assert!(!contract.nft_resolve_transfer(user1(), user2(), token_id.clone()));
+
+    assert_eq!(contract.nft_token(token_id).unwrap().owner_id, user1());
+    assert_eq!(contract.nft_supply_for_owner(user2()), U128(0));
+}
+
+#[test]
+fn test_nft_resolve_transfer_keeps_box() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+    let token_id = format!("{}:{}", quest.id, box_id);
+
+    testing_env!(context
+        .attached_deposit(ONE_NEAR / 100)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.nft_transfer_call(user2(), token_id.clone(), None, None, String::new());
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Successful(b"false".to_vec())],
+    );
+
+    assert!(contract.nft_resolve_transfer(user1(), user2(), token_id.clone()));
+
+    assert_eq!(contract.nft_token(token_id).unwrap().owner_id, user2());
+}
+
+#[test]
+fn test_transfer_box_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(ONE_NEAR / 100)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.transfer_box(quest.id, box_id, user2());
+
+    assert_eq!(
+        contract.questboxes_per_owner(user1(), quest.id, None).len(),
+        0
+    );
+    assert_eq!(
+        contract.questboxes_per_owner(user2(), quest.id, None).len(),
+        1
+    );
+    // the sender gave away the last box of the quest
+    assert_eq!(contract.get_users(quest.id, None), vec![user2()]);
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_EXPIRED")]
+fn test_transfer_expired_box_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_expiry(quest.id, None, Some(U64(DAY)));
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .block_timestamp(DAY)
+        .attached_deposit(ONE_NEAR / 100)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.transfer_box(quest.id, box_id, user2());
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_EXPIRED")]
+fn test_nft_transfer_expired_box_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_expiry(quest.id, None, Some(U64(DAY)));
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context.block_timestamp(DAY).build());
+
+    transfer_box_as_nft(
+        &mut contract,
+        &mut context,
+        user1(),
+        user2(),
+        &format!("{}:{}", quest.id, box_id),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be bigger than")]
+fn test_nft_transfer_without_storage_deposit_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.nft_transfer(user2(), format!("{}:{}", quest.id, box_id), None, None);
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be bigger than")]
+fn test_transfer_box_without_storage_deposit_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.transfer_box(quest.id, box_id, user2());
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_ALREADY_CLAIMED")]
+fn test_transfer_claimed_box_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1), None);
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+
+    testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+
+    contract.transfer_box(quest.id, box_id, user2());
+}
+
+#[test]
+#[should_panic(expected = "ERR_TRANSFERS_DISABLED")]
+fn test_transfer_box_when_disabled_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_transfers_enabled(quest.id, false);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(ONE_NEAR / 100)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.transfer_box(quest.id, box_id, user2());
+}
+
+#[test]
+#[should_panic(expected = "ERR_TRANSFERS_DISABLED")]
+fn test_nft_transfer_when_disabled_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_transfers_enabled(quest.id, false);
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    transfer_box_as_nft(
+        &mut contract,
+        &mut context,
+        user1(),
+        user2(),
+        &format!("{}:{}", quest.id, box_id),
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_set_transfers_enabled_by_non_owner_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.set_transfers_enabled(quest.id, false);
+}
+
+#[test]
+fn test_mint_sets_box_expiry() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_expiry(quest.id, None, Some(U64(10 * DAY)));
+    contract.set_box_expiry(quest.id, Some(BoxRarity::Epic), Some(U64(20 * DAY)));
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    contract.mint(quest.id, user1(), BoxRarity::Epic);
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert_eq!(boxes[0].expires_at, Some(U64(10 * DAY)));
+    assert_eq!(boxes[1].expires_at, Some(U64(20 * DAY)));
+
+    contract.set_box_expiry(quest.id, Some(BoxRarity::Epic), None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Epic);
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert_eq!(boxes[2].expires_at, Some(U64(10 * DAY)));
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_EXPIRED")]
+fn test_claim_expired_box_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_expiry(quest.id, None, Some(U64(DAY)));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1), None);
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .block_timestamp(DAY)
+        .build());
+
+    contract.claim(quest.id, box_id);
+}
+
+#[test]
+fn test_sweep_expired_boxes() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_expiry(quest.id, Some(BoxRarity::Rare), Some(U64(DAY)));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1), None);
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user1()]);
+    contract.mint(quest.id, user1(), BoxRarity::Epic);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, 0);
+
+    testing_env!(context
+        .predecessor_account_id(owner())
+        .block_timestamp(DAY)
+        .build());
+
+    assert_eq!(contract.sweep_expired_boxes(quest.id, None, None), vec![1]);
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert!(matches!(boxes[0].box_status, JsonBoxStatus::Claimed { .. }));
+    assert_eq!(boxes[1].box_status, JsonBoxStatus::Expired);
+    assert_eq!(boxes[2].box_status, JsonBoxStatus::NonClaimed);
+
+    // already swept boxes are skipped
+    assert_eq!(
+        contract.sweep_expired_boxes(quest.id, Some(1), Some(5)),
+        vec![]
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_EXPIRED")]
+fn test_claim_swept_box_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_expiry(quest.id, None, Some(U64(DAY)));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1), None);
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context.block_timestamp(DAY).build());
+
+    contract.sweep_expired_boxes(quest.id, None, None);
+
+    // even if the expiration is lifted, swept boxes stay expired
+    contract.set_box_expiry(quest.id, None, None);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim(quest.id, box_id);
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_sweep_expired_boxes_by_non_owner_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.sweep_expired_boxes(quest.id, None, None);
+}
+
+fn claim_many_and_verify(
+    contract: &mut Contract,
+    context: &mut VMContextBuilder,
+    claims: Vec<(u128, u32)>,
+) -> PromiseOrValue<Vec<Option<(u128, BoxRarity, JsonReward)>>> {
+    let escrowed = contract.quests.get(&0).unwrap().escrow_enabled;
+
+    // escrowed rewards are stored with the claim, so their storage is paid for
+    testing_env!(context
+        .attached_deposit(if escrowed { ONE_NEAR / 100 } else { 1 })
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim_many(0, claims.iter().map(|(box_id, _)| *box_id).collect());
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Successful(vec![])],
+    );
+
+    contract.check_iah_verification_and_claim_many_callback(0, user1(), claims, escrowed)
+}
+
+#[test]
+fn test_claim_many_merges_near_transfers() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(3), None);
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user1(), user1()]);
+
+    claim_many_and_verify(&mut contract, &mut context, vec![(0, 0), (1, 0), (2, 0)]);
+
+    let transfers = (0..3)
+        .map(|box_id| {
+            (
+                box_id,
+                0,
+                Reward::Near {
+                    amount: ONE_NEAR / 10,
+                },
+            )
+        })
+        .collect::<Vec<(u128, u32, Reward)>>();
+
+    // all the boxes are paid out with a single transfer, so there is a single result
+    testing_env!(
+        context.build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Successful(vec![])],
+    );
+
+    let results = contract.claim_many_transfer_callback(
+        quest.id,
+        user1(),
+        vec![(0, 0), (1, 0), (2, 0)],
+        transfers,
+    );
+
+    assert_eq!(
+        results,
+        (0..3)
+            .map(|box_id| Some((
+                box_id,
+                BoxRarity::Rare,
+                JsonReward::Near {
+                    amount: U128(ONE_NEAR / 10)
+                }
+            )))
+            .collect::<Vec<Option<(u128, BoxRarity, JsonReward)>>>()
+    );
+}
+
+#[test]
+fn test_claim_many_draws_each_box_separately() {
+    let (mut contract, mut context, quest) = setup(Some(25), None, None);
+
+    contract.add_near_range_reward(
+        quest.id,
+        BoxRarity::Rare,
+        U128(ONE_NEAR / 10),
+        U128(5 * ONE_NEAR),
+        U128(20 * ONE_NEAR),
+        None,
+    );
+
+    let box_ids = contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(); 5]);
+
+    // all the boxes are opened within a single receipt, so they share its random seed
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .random_seed([7; 32])
+        .build());
+
+    contract.claim_many(quest.id, box_ids.clone());
+
+    let amounts: Vec<u128> = box_ids
+        .iter()
+        .map(
+            |box_id| match claimed_reward(&contract, quest.id, *box_id) {
+                Reward::Near { amount } => amount,
+                _ => panic!("Box should be claimed with Near reward"),
+            },
+        )
+        .collect();
+
+    assert!(amounts.windows(2).any(|pair| pair[0] != pair[1]));
+}
+
+#[test]
+fn test_claim_many_failed_transfer_undoes_claims() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(2), None);
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user1()]);
+
+    claim_many_and_verify(&mut contract, &mut context, vec![(0, 0), (1, 0)]);
+
+    testing_env!(
+        context.build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed],
+    );
+
+    let results = contract.claim_many_transfer_callback(
+        quest.id,
+        user1(),
+        vec![(0, 0), (1, 0)],
+        vec![
+            (
+                0,
+                0,
+                Reward::Near {
+                    amount: ONE_NEAR / 10,
+                },
+            ),
+            (
+                1,
+                0,
+                Reward::Near {
+                    amount: ONE_NEAR / 10,
+                },
+            ),
+        ],
+    );
+
+    assert_eq!(results, vec![None, None]);
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert_eq!(boxes[0].box_status, JsonBoxStatus::NonClaimed);
+    assert_eq!(boxes[1].box_status, JsonBoxStatus::NonClaimed);
+    assert_eq!(
+        contract
+            .available_rewards(quest.id, BoxRarity::Rare, None)
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_claim_many_resolves_each_transfer_group() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    add_nft_tokens(&mut contract, &mut context, vec!["token_1", "token_2"]);
+
+    contract.trust_ft_contract(ft());
+
+    testing_env!(context.predecessor_account_id(ft()).build());
+
+    contract.ft_on_transfer(
+        owner(),
+        U128(200),
+        String::from(r#"{ "quest_id": 0, "rarity": "epic", "amount": "100", "capacity": "2" }"#),
+    );
+
+    testing_env!(context.predecessor_account_id(owner()).build());
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user1()]);
+    contract.mint_many(quest.id, BoxRarity::Epic, vec![user1(), user1()]);
+
+    claim_many_and_verify(
+        &mut contract,
+        &mut context,
+        vec![(2, 1), (3, 1), (0, 0), (1, 0)],
+    );
+
+    let claimed_token = |box_id: u128| match contract
+        .questboxes_per_owner(user1(), quest.id, None)
+        .remove(box_id as usize)
+        .box_status
+    {
+        JsonBoxStatus::Claimed {
+            reward: JsonReward::NonFungibleToken { token_id, .. },
+        } => token_id,
+        _ => unreachable!(),
+    };
+
+    let transfers = vec![
+        (
+            2,
+            1,
+            Reward::FungibleToken {
+                contract_id: ft(),
+                amount: 100,
+            },
+        ),
+        (
+            3,
+            1,
+            Reward::FungibleToken {
+                contract_id: ft(),
+                amount: 100,
+            },
+        ),
+        (
+            0,
+            0,
+            Reward::NonFungibleToken {
+                contract_id: nft(),
+                token_id: claimed_token(0),
+            },
+        ),
+        (
+            1,
+            0,
+            Reward::NonFungibleToken {
+                contract_id: nft(),
+                token_id: claimed_token(1),
+            },
+        ),
+    ];
+
+    // fungible tokens are merged, each NFT has its own transfer
+    testing_env!(
+        context.build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![
+            PromiseResult::Successful(vec![]),
+            PromiseResult::Failed,
+            PromiseResult::Successful(vec![]),
+        ],
+    );
+
+    let results = contract.claim_many_transfer_callback(
+        quest.id,
+        user1(),
+        vec![(2, 1), (3, 1), (0, 0), (1, 0)],
+        transfers,
+    );
+
+    assert_eq!(
+        results[0],
+        Some((
+            2,
+            BoxRarity::Epic,
+            JsonReward::FungibleToken {
+                contract_id: ft(),
+                amount: U128(100)
+            }
+        ))
+    );
+    assert!(results[1].is_some());
+    assert_eq!(results[2], None);
+    assert!(matches!(
+        results[3],
+        Some((1, BoxRarity::Rare, JsonReward::NonFungibleToken { .. }))
+    ));
+}
+
+#[test]
+fn test_claim_many_in_escrow_mode_skips_transfers() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_escrow_mode(quest.id, true);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(2), None);
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user1()]);
+
+    match claim_many_and_verify(&mut contract, &mut context, vec![(0, 0), (1, 0)]) {
+        PromiseOrValue::Value(results) => assert_eq!(results.len(), 2),
+        PromiseOrValue::Promise(_) => panic!("Rewards shouldn't be transferred"),
+    };
+
+    assert_eq!(contract.pending_rewards(user1()).len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "ERR_TOO_MANY_BOXES")]
+fn test_claim_many_too_many_boxes_panics() {
+    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(); 11]);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim_many(quest.id, (0..11).collect());
+}
+
+#[test]
+#[should_panic(expected = "ERR_DUPLICATED_BOXES")]
+fn test_claim_many_duplicated_boxes_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(2), None);
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim_many(quest.id, vec![0, 0]);
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_NOT_FOUND")]
+fn test_claim_many_foreign_box_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    contract.mint(quest.id, user2(), BoxRarity::Rare);
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    contract.claim_many(quest.id, vec![0, 1]);
+}
+
+fn box_key() -> PublicKey {
+    PublicKey::from_str("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp").unwrap()
+}
+
+fn claim_with_box_key(
+    contract: &mut Contract,
+    context: &mut VMContextBuilder,
+    receiver_id: AccountId,
+) {
+    // the access key is added to the contract account, so it calls itself
+    testing_env!(context
+        .attached_deposit(0)
+        .predecessor_account_id(owner())
+        .signer_account_pk(box_key())
+        .build());
+
+    contract.claim_keyed(receiver_id);
+}
+
+#[test]
+fn test_mint_keyed_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let box_id = contract.mint_keyed(quest.id, box_key(), BoxRarity::Epic);
+
+    let keyed_box = contract.keyed_box(box_key()).expect("Box should exist");
+
+    assert_eq!(keyed_box.box_id, box_id);
+    assert_eq!(keyed_box.box_rarity, BoxRarity::Epic);
+    assert_eq!(
+        contract
+            .nft_token(format!("{}:{}", quest.id, box_id))
+            .unwrap()
+            .owner_id,
+        owner()
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_KEY_ALREADY_EXISTS")]
+fn test_mint_keyed_with_same_key_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint_keyed(quest.id, box_key(), BoxRarity::Epic);
+    contract.mint_keyed(quest.id, box_key(), BoxRarity::Rare);
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be bigger than")]
+fn test_mint_keyed_without_allowance_deposit_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.attached_deposit(ONE_NEAR / 100).build());
+
+    contract.mint_keyed(quest.id, box_key(), BoxRarity::Epic);
+}
+
+#[test]
+fn test_claim_keyed_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Epic, U128(ONE_NEAR / 10), U64(1), None);
+    let box_id = contract.mint_keyed(quest.id, box_key(), BoxRarity::Epic);
+
+    claim_with_box_key(&mut contract, &mut context, user2());
+
+    assert!(contract.keyed_box(box_key()).is_none());
+
+    let boxes = contract.questboxes_per_owner(user2(), quest.id, None);
+
+    assert_eq!(boxes[0].box_id, box_id);
+    assert_eq!(
+        boxes[0].box_status,
+        JsonBoxStatus::Claimed {
+            reward: JsonReward::Near {
+                amount: U128(ONE_NEAR / 10)
+            }
+        }
+    );
+}
+
+#[test]
+fn test_claim_keyed_doesnt_refund_allowance() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Epic, U128(ONE_NEAR / 10), U64(1), None);
+    contract.mint_keyed(quest.id, box_key(), BoxRarity::Epic);
+
+    claim_with_box_key(&mut contract, &mut context, user2());
+
+    // the minter doesn't get anything back, the allowance went to the claim fees
+    assert!(get_created_receipts().iter().all(|receipt| {
+        receipt.receiver_id != owner()
+            || !receipt
+                .actions
+                .iter()
+                .any(|action| matches!(action, VmAction::Transfer { .. }))
+    }));
+}
+
+#[test]
+#[should_panic(expected = "ERR_KEY_NOT_FOUND")]
+fn test_claim_keyed_twice_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Epic, U128(ONE_NEAR / 10), U64(2), None);
+    contract.mint_keyed(quest.id, box_key(), BoxRarity::Epic);
+
+    claim_with_box_key(&mut contract, &mut context, user2());
+    claim_with_box_key(&mut contract, &mut context, user3());
+}
+
+#[test]
+#[should_panic(expected = "ERR_KEY_NOT_FOUND")]
+fn test_claim_keyed_after_delete_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.add_near_reward(quest.id, BoxRarity::Epic, U128(ONE_NEAR / 10), U64(1), None);
+    let box_id = contract.mint_keyed(quest.id, box_key(), BoxRarity::Epic);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.delete_boxes(quest.id, vec![box_id]);
+
+    assert!(contract.keyed_box(box_key()).is_none());
+
+    claim_with_box_key(&mut contract, &mut context, user2());
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_claim_keyed_by_another_account_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.mint_keyed(quest.id, box_key(), BoxRarity::Epic);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .signer_account_pk(box_key())
+        .build());
+
+    contract.claim_keyed(user1());
+}
+
+fn voucher_keypair() -> Keypair {
+    let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+    let public = (&secret).into();
+
+    Keypair { secret, public }
+}
+
+fn voucher_key() -> PublicKey {
+    // ed25519 keys start with the curve type
+    PublicKey::try_from([vec![0], voucher_keypair().public.to_bytes().to_vec()].concat()).unwrap()
+}
+
+fn voucher(receiver_id: AccountId, nonce: u64) -> JsonMintVoucher {
+    JsonMintVoucher {
+        quest_id: 0,
+        rarity: BoxRarity::Epic,
+        receiver_id,
+        nonce: U64(nonce),
+        expires_at: U64(DAY),
+    }
+}
+
+fn sign_voucher(voucher: &JsonMintVoucher) -> Base64VecU8 {
+    Base64VecU8(
+        voucher_keypair()
+            .sign(&voucher.to_message())
+            .to_bytes()
+            .to_vec(),
+    )
+}
+
+fn redeem_voucher(
+    contract: &mut Contract,
+    context: &mut VMContextBuilder,
+    voucher: JsonMintVoucher,
+    signature: Base64VecU8,
+) -> BoxId {
+    // anyone holding the voucher can redeem it, the receiver gets the box
+    testing_env!(context
+        .predecessor_account_id(user3())
+        .attached_deposit(ONE_NEAR)
+        .build());
+
+    contract.redeem_voucher(voucher, signature)
+}
+
+#[test]
+fn test_redeem_voucher_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_voucher_key(quest.id, Some(voucher_key()));
+
+    let voucher = voucher(user1(), 42);
+    let signature = sign_voucher(&voucher);
+
+    let box_id = redeem_voucher(&mut contract, &mut context, voucher, signature);
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert_eq!(boxes[0].box_id, box_id);
+    assert_eq!(boxes[0].box_rarity, BoxRarity::Epic);
+    assert_eq!(contract.get_voucher_key(quest.id), Some(voucher_key()));
+    assert!(contract.is_voucher_redeemed(quest.id, U64(42)));
+    assert!(!contract.is_voucher_redeemed(quest.id, U64(43)));
+
+    // the redeemer paid for the storage, so gets it back on deletion
+    let questbox = contract
+        .quests
+        .get(&quest.id)
+        .unwrap()
+        .boxes
+        .get(&box_id)
+        .unwrap();
+
+    assert_eq!(questbox.minter_id, Some(user3()));
+}
+
+#[test]
+#[should_panic(expected = "ERR_VOUCHER_ALREADY_REDEEMED")]
+fn test_redeem_voucher_twice_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_voucher_key(quest.id, Some(voucher_key()));
+
+    let voucher = voucher(user1(), 42);
+    let signature = sign_voucher(&voucher);
+
+    redeem_voucher(
+        &mut contract,
+        &mut context,
+        voucher.clone(),
+        signature.clone(),
+    );
+    redeem_voucher(&mut contract, &mut context, voucher, signature);
+}
+
+#[test]
+#[should_panic(expected = "ERR_VOUCHER_EXPIRED")]
+fn test_redeem_expired_voucher_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_voucher_key(quest.id, Some(voucher_key()));
+
+    let voucher = voucher(user1(), 42);
+    let signature = sign_voucher(&voucher);
+
+    testing_env!(context.block_timestamp(DAY).build());
+
+    redeem_voucher(&mut contract, &mut context, voucher, signature);
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_VOUCHER_SIGNATURE")]
+fn test_redeem_voucher_for_another_receiver_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_voucher_key(quest.id, Some(voucher_key()));
+
+    let signature = sign_voucher(&voucher(user1(), 42));
+
+    redeem_voucher(&mut contract, &mut context, voucher(user2(), 42), signature);
+}
+
+#[test]
+#[should_panic(expected = "ERR_VOUCHERS_NOT_ACCEPTED")]
+fn test_redeem_voucher_without_voucher_key_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    let voucher = voucher(user1(), 42);
+    let signature = sign_voucher(&voucher);
+
+    redeem_voucher(&mut contract, &mut context, voucher, signature);
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be bigger than")]
+fn test_redeem_voucher_without_storage_deposit_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_voucher_key(quest.id, Some(voucher_key()));
+
+    let voucher = voucher(user1(), 42);
+    let signature = sign_voucher(&voucher);
+
+    testing_env!(context
+        .predecessor_account_id(user3())
+        .attached_deposit(0)
+        .build());
+
+    contract.redeem_voucher(voucher, signature);
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_set_voucher_key_by_non_owner_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.predecessor_account_id(user1()).build());
+
+    contract.set_voucher_key(quest.id, Some(voucher_key()));
+}
+
+#[test]
+#[should_panic(expected = "ERR_VOUCHER_KEY_NOT_ED25519")]
+fn test_set_secp256k1_voucher_key_panics() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_voucher_key(quest.id, Some(PublicKey::try_from(vec![1; 65]).unwrap()));
+}
+
+#[test]
+fn test_buy_box_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(
+        quest.id,
+        BoxRarity::Rare,
+        Some(U128(ONE_NEAR)),
+        Some(U64(3)),
+    );
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(3 * ONE_NEAR)
+        .build());
+
+    let box_ids = contract.buy_box(quest.id, BoxRarity::Rare, 2);
+
+    assert_eq!(box_ids, vec![0, 1]);
+    assert_eq!(
+        contract.questboxes_supply_per_owner(user1(), quest.id),
+        U128(2)
+    );
+    assert_eq!(contract.get_revenue(quest.id), U128(2 * ONE_NEAR));
+
+    assert_eq!(
+        contract.get_box_sales(quest.id),
+        vec![JsonBoxSale {
+            rarity: BoxRarity::Rare,
+            price: U128(ONE_NEAR),
+            supply: Some(U64(3)),
+            sold: U64(2),
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_NOT_FOR_SALE")]
+fn test_buy_box_not_for_sale_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR * 2)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Epic, 1);
+}
+
+#[test]
+#[should_panic(expected = "ERR_NOT_ENOUGH_BOXES_FOR_SALE")]
+fn test_buy_box_over_supply_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(
+        quest.id,
+        BoxRarity::Rare,
+        Some(U128(ONE_NEAR)),
+        Some(U64(1)),
+    );
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR * 3)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 2);
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be bigger than")]
+fn test_buy_box_without_enough_deposit_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 1);
+}
+
+#[test]
+#[should_panic(expected = "ERR_BOX_NOT_FOR_SALE")]
+fn test_buy_box_after_sale_stopped_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+    contract.set_box_sale(quest.id, BoxRarity::Rare, None, None);
+
+    assert_eq!(contract.get_box_sales(quest.id).len(), 0);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR * 2)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 1);
+}
+
+#[test]
+fn test_buy_box_takes_fee_from_price() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    // 2.5%
+    contract.set_fee(250);
+
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(2 * ONE_NEAR)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 1);
+
+    assert_eq!(
+        contract.get_revenue(quest.id),
+        U128(ONE_NEAR - ONE_NEAR / 40)
+    );
+    assert_eq!(
+        contract.get_quest_fees(quest.id).collected,
+        U128(ONE_NEAR / 40)
+    );
+}
+
+#[test]
+#[should_panic(expected = "Box 0 was sold")]
+fn test_delete_sold_box_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(2 * ONE_NEAR)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 1);
+
+    testing_env!(context
+        .predecessor_account_id(owner())
+        .attached_deposit(1)
+        .build());
+
+    contract.delete_boxes(quest.id, vec![0]);
+}
+
+#[test]
+fn test_withdraw_revenue_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR * 2)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 1);
+
+    testing_env!(context
+        .predecessor_account_id(owner())
+        .attached_deposit(1)
+        .build());
+
+    contract.withdraw_revenue(quest.id);
+
+    assert_eq!(contract.get_revenue(quest.id), U128(0));
+}
+
+#[test]
+fn test_withdraw_revenue_restores_revenue_on_failure() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR * 2)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 1);
+
+    let revenue = contract.get_revenue(quest.id);
+
+    testing_env!(context
+        .predecessor_account_id(owner())
+        .attached_deposit(1)
+        .build());
+
+    contract.withdraw_revenue(quest.id);
+
+    testing_env!(
+        context.predecessor_account_id(owner()).build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![PromiseResult::Failed],
+    );
+
+    assert!(!contract.withdraw_revenue_callback(quest.id, owner(), revenue));
+    assert_eq!(contract.get_revenue(quest.id), revenue);
+}
+
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_withdraw_revenue_by_non_owner_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR * 2)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 1);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.withdraw_revenue(quest.id);
+}
+
+#[test]
+#[should_panic(expected = "ERR_MAX_BOXES_BY_RARITY_REACHED")]
+fn test_mint_over_max_boxes_by_rarity_with_panic() {
+    let (mut contract, _, quest) = setup(None, None, None);
+
+    contract.set_max_boxes(quest.id, Some(BoxRarity::Rare), Some(U64(2)));
+
+    contract.mint(quest.id, user1(), BoxRarity::Epic);
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user2(), user3()]);
+}
+
+#[test]
+#[should_panic(expected = "ERR_MAX_BOXES_REACHED")]
+fn test_mint_over_max_boxes_with_panic() {
+    let (mut contract, _, quest) = setup(None, None, None);
+
+    contract.set_max_boxes(quest.id, None, Some(U64(2)));
+
+    contract.mint(quest.id, user1(), BoxRarity::Epic);
+    contract.mint(quest.id, user2(), BoxRarity::Rare);
+    contract.mint(quest.id, user3(), BoxRarity::Legendary);
+}
+
+#[test]
+#[should_panic(expected = "ERR_MAX_BOXES_PER_ACCOUNT_REACHED")]
+fn test_mint_over_max_boxes_per_account_with_panic() {
+    let (mut contract, _, quest) = setup(None, None, None);
+
+    contract.set_max_boxes_per_account(quest.id, Some(U64(1)));
+
+    contract.mint(quest.id, user1(), BoxRarity::Epic);
+    contract.mint(quest.id, user2(), BoxRarity::Epic);
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+}
+
+#[test]
+fn test_mint_after_delete_within_max_boxes_per_account() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_max_boxes_per_account(quest.id, Some(U64(1)));
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Epic);
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.delete_boxes(quest.id, vec![box_id]);
+
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    assert_eq!(
+        contract.questboxes_per_owner(user1(), quest.id, None).len(),
+        1
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_MAX_BOXES_PER_ACCOUNT_REACHED")]
+fn test_buy_box_over_max_boxes_per_account_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.set_max_boxes_per_account(quest.id, Some(U64(1)));
+    contract.set_box_sale(quest.id, BoxRarity::Rare, Some(U128(ONE_NEAR)), None);
+
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR * 3)
+        .build());
+
+    contract.buy_box(quest.id, BoxRarity::Rare, 2);
+}
+
+#[test]
+#[should_panic(expected = "ERR_MAX_BOXES_LOWER_THAN_MINTED")]
+fn test_set_max_boxes_lower_than_minted_with_panic() {
+    let (mut contract, _, quest) = setup(None, None, None);
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user2()]);
+
+    contract.set_max_boxes(quest.id, Some(BoxRarity::Rare), Some(U64(1)));
+}
+
+#[test]
+#[should_panic(expected = "Deposited amount must be bigger than")]
+fn test_set_max_boxes_without_storage_deposit_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.attached_deposit(0).build());
+
+    contract.set_max_boxes(quest.id, Some(BoxRarity::Rare), Some(U64(5)));
+}
+
+#[test]
+fn test_box_capacity_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    testing_env!(context.attached_deposit(ONE_NEAR * 10).build());
+
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(5), None);
+
+    contract.set_max_boxes(quest.id, Some(BoxRarity::Rare), Some(U64(5)));
+    contract.set_max_boxes(quest.id, None, Some(U64(4)));
+
+    contract.mint_many(quest.id, BoxRarity::Rare, vec![user1(), user2()]);
+
+    assert_eq!(
+        contract.box_capacity(quest.id, BoxRarity::Rare),
+        JsonBoxCapacity {
+            rarity: BoxRarity::Rare,
+            minted: U64(2),
+            max_boxes: Some(U64(5)),
+            remaining: Some(U64(2)),
+            funded: U64(5),
+        }
+    );
+
+    testing_env!(context.attached_deposit(1).build());
+
+    // deleting a box frees its slot
+    contract.delete_boxes(quest.id, vec![0]);
+
+    let capacity = contract.box_capacity(quest.id, BoxRarity::Rare);
+
+    assert_eq!(capacity.minted, U64(1));
+    assert_eq!(capacity.remaining, Some(U64(3)));
+}
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(1));
+fn custom_tiers() -> Vec<JsonRarityTier> {
+    vec![
+        JsonRarityTier {
+            rarity: BoxRarity::new("common"),
+            media: Some("bafkreicommon".to_string()),
+            probability: Some(Probability {
+                numerator: 1,
+                denominator: 2,
+            }),
+        },
+        JsonRarityTier {
+            rarity: BoxRarity::new("mythic"),
+            media: Some("bafkreimythic".to_string()),
+            probability: None,
+        },
+    ]
+}
 
-    contract.mint(quest.id, user1(), BoxRarity::Rare);
-    contract.mint(quest.id, user2(), BoxRarity::Epic);
+#[test]
+fn test_default_rarity_tiers() {
+    let (contract, _, quest) = setup(None, None, None);
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(2));
-    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(1));
+    let tiers = contract.rarity_tiers(quest.id);
 
-    contract.mint_many(
-        quest.id,
-        BoxRarity::Legendary,
-        vec![user1(), user2(), user1()],
+    assert_eq!(
+        tiers
+            .iter()
+            .map(|tier| tier.rarity.clone())
+            .collect::<Vec<_>>(),
+        BoxRarity::all()
+    );
+    assert_eq!(tiers[0].media, BoxRarity::Rare.to_media_ipfs());
+    assert_eq!(
+        near_sdk::serde_json::to_string(&BoxRarity::Epic).unwrap(),
+        "\"epic\""
     );
-
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(4));
-    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(2));
 }
 
 #[test]
-fn test_quest_boxes_differ_after_mint_many() {
-    let (mut contract, mut context, quest) = setup(Some(10), None, None);
+fn test_set_rarity_tiers_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(0));
-    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(0));
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
 
-    contract.mint_many(
-        quest.id,
-        BoxRarity::Rare,
-        vec![user1(), user2()],
-    );
+    contract.set_rarity_tiers(quest.id, custom_tiers());
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(1));
-    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(1));
+    assert_eq!(contract.rarity_tiers(quest.id), custom_tiers());
 
-    contract.mint_many(
+    contract.add_near_reward(
         quest.id,
-        BoxRarity::Rare,
-        vec![user1(), user2()],
+        BoxRarity::new("mythic"),
+        U128(ONE_NEAR / 10),
+        U64(1),
+        None,
     );
+    contract.mint(quest.id, user1(), BoxRarity::new("mythic"));
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(2));
-    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(2));
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
 
-    let pagination = Some(Pagination::new(1, 40));
+    assert_eq!(boxes[0].box_rarity, BoxRarity::new("mythic"));
+    assert_eq!(boxes[0].ipfs, "bafkreimythic");
+}
 
-    let user_1_quest_boxes = contract.questboxes_per_owner(user1(), quest.id, pagination.clone());
-    let user_2_quest_boxes = contract.questboxes_per_owner(user2(), quest.id, pagination.clone());
+#[test]
+#[should_panic(expected = "Rarity rare isn't a tier of the quest")]
+fn test_mint_default_rarity_with_custom_tiers_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
 
-    let user_1_quest_box = user_1_quest_boxes.get(1).unwrap();
-    let user_2_quest_box = user_2_quest_boxes.get(1).unwrap();
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
 
-    assert_ne!(user_1_quest_box.box_id, user_2_quest_box.box_id, "Box id's should be different");
+    contract.set_rarity_tiers(quest.id, custom_tiers());
+
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
 }
 
 #[test]
-fn test_quest_per_owner_differ_per_quest() {
-    let (mut contract, mut context, quest) = setup(Some(10), None, None);
-
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(0));
-    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(0));
-
-    contract.mint_many(
-        quest.id,
-        BoxRarity::Rare,
-        vec![user1(), user2()],
-    );
+#[should_panic(expected = "Rarity mythic isn't a tier of the quest")]
+fn test_add_reward_with_unknown_rarity_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(1));
-    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(1));
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
 
-    contract.mint_many(
+    contract.add_near_reward(
         quest.id,
-        BoxRarity::Rare,
-        vec![user1(), user2()],
+        BoxRarity::new("mythic"),
+        U128(ONE_NEAR / 10),
+        U64(1),
+        None,
     );
+}
 
-    assert_eq!(contract.questboxes_supply_per_owner(user1(), quest.id), U128(2));
-    assert_eq!(contract.questboxes_supply_per_owner(user2(), quest.id), U128(2));
-
-    let pagination = Some(Pagination::new(1, 40));
+#[test]
+#[should_panic(expected = "ERR_TIERS_LOCKED")]
+fn test_set_rarity_tiers_after_mint_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
 
-    let user_1_quest_boxes = contract.questboxes_per_owner(user1(), quest.id, pagination.clone());
-    let user_2_quest_boxes = contract.questboxes_per_owner(user2(), quest.id, pagination.clone());
+    contract.mint(quest.id, user1(), BoxRarity::Rare);
 
-    let user_1_quest_box = user_1_quest_boxes.get(1).unwrap();
-    let user_2_quest_box = user_2_quest_boxes.get(1).unwrap();
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
 
-    assert_ne!(user_1_quest_box.box_id, user_2_quest_box.box_id, "Box id's should be different");
+    contract.set_rarity_tiers(quest.id, custom_tiers());
 }
 
 #[test]
-fn test_boxes_for_owner_amount() {
+#[should_panic(expected = "ERR_TIER_MEDIA_REQUIRED")]
+fn test_set_rarity_tiers_without_media_with_panic() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    testing_env!(context.attached_deposit(ONE_NEAR * 2).build());
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
 
-    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    let mut tiers = custom_tiers();
+    tiers[1].media = None;
 
-    assert_eq!(contract.questboxes_per_owner(user1(), quest.id, None).len(), 1);
+    contract.set_rarity_tiers(quest.id, tiers);
+}
 
-    contract.mint(quest.id, user1(), BoxRarity::Epic);
-    contract.mint(quest.id, user1(), BoxRarity::Epic);
+#[test]
+#[should_panic(expected = "ERR_INVALID_RARITY_NAME")]
+fn test_set_rarity_tiers_with_invalid_name_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.mint(quest.id, user1(), BoxRarity::Legendary);
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
 
-    assert_eq!(contract.questboxes_per_owner(user1(), quest.id, None).len(), 4);
+    let mut tiers = custom_tiers();
+    tiers[0].rarity = BoxRarity::new("Common Tier");
 
-    contract.mint(quest.id, user2(), BoxRarity::Epic);
+    contract.set_rarity_tiers(quest.id, tiers);
+}
 
-    assert_eq!(contract.questboxes_per_owner(user1(), quest.id, None).len(), 4);
-    assert_eq!(contract.questboxes_per_owner(user2(), quest.id, None).len(), 1);
+fn brand_media() -> BoxMedia {
+    BoxMedia {
+        media: "https://example.com/box.png".to_string(),
+        media_hash: Some(Base64VecU8(vec![7; 32])),
+    }
 }
 
 #[test]
-fn test_boxes_for_owner_status() {
+fn test_set_box_media_succeeds() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
+
+    contract.set_box_media(quest.id, BoxRarity::Epic, Some(brand_media()));
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Epic);
     contract.mint(quest.id, user1(), BoxRarity::Rare);
 
+    assert_eq!(contract.box_media(quest.id, BoxRarity::Epic), brand_media());
+
     let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
 
-    let box_data = boxes.get(0).unwrap().to_owned();
+    assert_eq!(boxes[0].ipfs, brand_media().media);
+    assert_eq!(boxes[0].media_hash, brand_media().media_hash);
+    // other rarities keep the defaults
+    assert_eq!(Some(boxes[1].ipfs.clone()), BoxRarity::Rare.to_media_ipfs());
+    assert_eq!(boxes[1].media_hash, None);
 
-    assert_eq!(box_data.box_status, JsonBoxStatus::NonClaimed);
+    let metadata = contract
+        .nft_token(format!("{}:{}", quest.id, box_id))
+        .and_then(|token| token.metadata)
+        .unwrap();
+
+    assert_eq!(metadata.media, Some(brand_media().media));
+    assert_eq!(metadata.media_hash, brand_media().media_hash);
+
+    contract.set_box_media(quest.id, BoxRarity::Epic, None);
+
+    assert_eq!(
+        contract.box_media(quest.id, BoxRarity::Epic),
+        BoxMedia::new(BoxRarity::Epic.to_media_ipfs().unwrap())
+    );
 }
 
 #[test]
-fn test_boxes_for_quest_for_owner_amount() {
+#[should_panic(expected = "ERR_INVALID_MEDIA_HASH")]
+fn test_set_box_media_with_invalid_hash_with_panic() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    let new_quest_id = contract.create_quest(&"new quest".to_string());
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
 
-    contract.mint(quest.id, user1(), BoxRarity::Rare);
+    let mut media = brand_media();
+    media.media_hash = Some(Base64VecU8(vec![7; 16]));
 
-    assert_eq!(
-        contract
-            .questboxes_for_quest_per_owner(quest.id, user1(), None)
-            .len(),
-        1
-    );
+    contract.set_box_media(quest.id, BoxRarity::Epic, Some(media));
+}
 
-    contract.mint(new_quest_id, user1(), BoxRarity::Epic);
-    contract.mint(new_quest_id, user1(), BoxRarity::Epic);
+#[test]
+#[should_panic(expected = "ERR_FORBIDDEN")]
+fn test_set_box_media_by_non_owner_with_panic() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.mint(quest.id, user1(), BoxRarity::Legendary);
+    testing_env!(context
+        .predecessor_account_id(user1())
+        .attached_deposit(ONE_NEAR)
+        .build());
 
-    assert_eq!(
-        contract
-            .questboxes_for_quest_per_owner(new_quest_id, user1(), None)
-            .len(),
-        2
-    );
-    assert_eq!(
-        contract
-            .questboxes_for_quest_per_owner(quest.id, user1(), None)
-            .len(),
-        2
-    );
+    contract.set_box_media(quest.id, BoxRarity::Epic, Some(brand_media()));
 }
 
 #[test]
@@ -863,7 +5073,7 @@ fn test_claim_as_another_user_with_panic() {
 fn test_claim_non_existing_with_panic() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(1), None);
     let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
     testing_env!(context
@@ -883,7 +5093,7 @@ fn test_claim_non_existing_with_panic() {
 fn test_claim_twice_with_panic() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(5));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 10), U64(5), None);
     let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
     testing_env!(context
@@ -899,7 +5109,7 @@ fn test_claim_twice_with_panic() {
 fn test_claim_box_status() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
     let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
     testing_env!(context
@@ -946,7 +5156,7 @@ fn test_claim_box_status() {
 fn test_claiming_one_box_doesnt_affect_others() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
     let box_1_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
     let box_2_id = contract.mint(quest.id, user2(), BoxRarity::Rare);
 
@@ -984,7 +5194,7 @@ fn test_claim_box_with_zero_probability() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
     contract.set_probability(quest.id, BoxRarity::Rare, Probability::ZERO);
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
     let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
     testing_env!(context
@@ -1033,7 +5243,7 @@ fn test_quest_boxes_differ_for_different_quests() {
 fn test_claim_decreases_reward_availability() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
     let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
     testing_env!(context
@@ -1048,7 +5258,7 @@ fn test_claim_decreases_reward_availability() {
 
     assert_eq!(rewards.len(), 1);
 
-    let reward = rewards.get(0).unwrap().to_owned();
+    let reward = rewards.first().unwrap().rewards.to_owned();
 
     // availability decreased
     assert_eq!(
@@ -1067,7 +5277,7 @@ fn test_claim_empty_reward_availability() {
 
     testing_env!(context.attached_deposit(3 * ONE_NEAR).build());
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(1));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(1), None);
     let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
     testing_env!(context
@@ -1087,7 +5297,7 @@ fn test_claim_empty_reward_availability() {
 fn test_claim_near_reward_succeeds() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(2), None);
     let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
     testing_env!(context
@@ -1161,13 +5371,59 @@ fn test_claim_nft_reward_succeeds() {
     contract.claim(quest.id, box_id);
 }
 
+#[test]
+fn test_claim_ft_reward_succeeds() {
+    let (mut contract, mut context, quest) = setup(None, None, None);
+
+    contract.trust_ft_contract(ft());
+
+    let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
+
+    testing_env!(context.predecessor_account_id(ft()).build());
+
+    contract.ft_on_transfer(
+        owner(),
+        U128(200),
+        String::from(
+            r#"
+        {
+            "quest_id": 0,
+            "rarity": "rare",
+            "amount": "100",
+            "capacity": "2"
+        }
+        "#,
+        ),
+    );
+
+    testing_env!(context
+        .attached_deposit(1)
+        .predecessor_account_id(user1())
+        .build());
+
+    // promises aren't called
+    contract.claim(quest.id, box_id);
+
+    let boxes = contract.questboxes_per_owner(user1(), quest.id, None);
+
+    assert_eq!(
+        boxes.first().unwrap().box_status,
+        JsonBoxStatus::Claimed {
+            reward: JsonReward::FungibleToken {
+                contract_id: ft(),
+                amount: U128(100),
+            },
+        }
+    );
+}
+
 #[test]
 fn test_claim_for_multiple_pools_succeeds() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(1));
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 2), U64(2));
-    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 4), U64(4));
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR), U64(1), None);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 2), U64(2), None);
+    contract.add_near_reward(quest.id, BoxRarity::Rare, U128(ONE_NEAR / 4), U64(4), None);
 
     let box_id = contract.mint(quest.id, user1(), BoxRarity::Rare);
 
@@ -1185,7 +5441,7 @@ fn test_claim_for_multiple_pools_succeeds() {
 fn test_check_verification_and_claim_callback_by_someone_with_panic() {
     let (mut contract, mut context, quest) = setup(None, None, None);
 
-    contract.check_iah_verification_and_claim_callback(quest.id, user1(), 0, 0);
+    contract.check_iah_verification_and_claim_callback(quest.id, user1(), 0, 0, false);
 }
 
 #[test]
@@ -1300,3 +5556,229 @@ fn test_mainnet_default_nft_contracts() {
 
     assert_eq!(contract.get_trusted_nft_contracts().len(), 10);
 }
+
+#[test]
+fn test_testnet_default_ft_contracts() {
+    let (mut contract, mut context, quest) =
+        setup(None, Some(testnet_user()), Some(testnet_user()));
+
+    assert_eq!(contract.get_trusted_ft_contracts().len(), 2);
+}
+
+#[test]
+fn test_mainnet_default_ft_contracts() {
+    let (mut contract, mut context, quest) =
+        setup(None, Some(mainnet_user()), Some(mainnet_user()));
+
+    assert_eq!(contract.get_trusted_ft_contracts().len(), 3);
+}
+
+// state of the first release with a Near and an NFT pool and a single box of user1
+fn write_legacy_state() {
+    let quest_id: QuestId = 0;
+    let quest_hash = env::sha256_array(&quest_id.to_be_bytes());
+
+    let mut pools = LookupMap::new(StorageKey::Pools { quest_hash });
+    pools.insert(
+        &0,
+        &LegacyPool {
+            id: 0,
+            rarity: BoxRarity::Rare,
+            kind: LegacyPoolKind::Near(NearPoolKind::new(ONE_NEAR, 2)),
+        },
+    );
+    pools.insert(
+        &1,
+        &LegacyPool {
+            id: 1,
+            rarity: BoxRarity::Epic,
+            kind: LegacyPoolKind::NonFungibleToken(LegacyNonFungibleTokenPoolKind {
+                contract_id: nft(),
+                tokens: BTreeSet::from(["1".to_string()]),
+                available_tokens: BTreeSet::from(["1".to_string()]),
+            }),
+        },
+    );
+
+    let mut pool_ids_by_rarity = LookupMap::new(StorageKey::PoolsByRarity { quest_hash });
+    pool_ids_by_rarity.insert(&BoxRarity::Rare, &HashSet::from([0]));
+    pool_ids_by_rarity.insert(&BoxRarity::Epic, &HashSet::from([1]));
+
+    // boxes didn't have the expiration and the minter yet
+    let mut legacy_boxes = LookupMap::new(StorageKey::Boxes { quest_hash });
+    legacy_boxes.insert(
+        &(0 as BoxId),
+        &(
+            0 as BoxId,
+            BoxRarity::Rare,
+            BoxStatus::NonClaimed,
+            quest_id,
+            user1(),
+        ),
+    );
+
+    let mut users = UnorderedSet::new(StorageKey::Users { quest_hash });
+    users.insert(&user1());
+
+    let mut quests = LookupMap::new(StorageKey::Quests);
+    quests.insert(
+        &quest_id,
+        &LegacyQuest {
+            id: quest_id,
+            title: "first quest".to_string(),
+            owner_id: owner(),
+            next_pool_id: 2,
+            pools,
+            nft_pool_by_key: LookupMap::new(StorageKey::NftPoolByKey { quest_hash }),
+            pool_ids_by_rarity,
+            next_box_id: 1,
+            boxes: LookupMap::new(StorageKey::Boxes { quest_hash }),
+            probability_by_rarity: LookupMap::new(StorageKey::ProbabilityByRarity { quest_hash }),
+            users,
+        },
+    );
+
+    let mut owner_quests = UnorderedSet::new(StorageKey::QuestIdsPerOwner {
+        account_hash: env::sha256_array(owner().as_bytes()),
+    });
+    owner_quests.insert(&quest_id);
+
+    let mut quests_per_owner = LookupMap::new(StorageKey::QuestsPerOwner);
+    quests_per_owner.insert(&owner(), &owner_quests);
+
+    let mut user_boxes = UnorderedSet::new(StorageKey::QuestBoxesData {
+        account_hash: env::sha256_array(user1().as_bytes()),
+    });
+    user_boxes.insert(&QuestBoxData::new(quest_id, 0));
+
+    let mut questboxes_per_owner = LookupMap::new(StorageKey::QuestBoxesPerOwner);
+    questboxes_per_owner.insert(&user1(), &user_boxes);
+
+    env::state_write(&LegacyContract {
+        quests,
+        quests_per_owner,
+        questboxes_per_owner,
+        next_quest_id: 1,
+        trusted_nft_contracts: UnorderedSet::new(StorageKey::TrustedNftContracts),
+    });
+}
+
+fn setup_legacy() -> (Contract, VMContextBuilder) {
+    let mut context = VMContextBuilder::new();
+
+    context.current_account_id(owner());
+    context.predecessor_account_id(owner());
+    context.account_balance(50 * ONE_NEAR);
+
+    testing_env!(context.build());
+
+    write_legacy_state();
+
+    (Contract::migrate(), context)
+}
+
+#[test]
+fn test_migrate_converts_legacy_state() {
+    let (mut contract, mut context) = setup_legacy();
+
+    assert_eq!(
+        contract.rewards(0, BoxRarity::Rare, None)[0].rewards,
+        JsonPoolRewards::Near {
+            amount: U128(ONE_NEAR),
+            available: 2,
+            total: 2,
+        }
+    );
+    assert_eq!(
+        contract.rewards(0, BoxRarity::Epic, None)[0].rewards,
+        JsonPoolRewards::NonFungibleToken {
+            contract_id: nft(),
+            token_ids: vec!["1".to_string()],
+            token_weights: Default::default(),
+            total: 1,
+        }
+    );
+
+    let boxes = contract.questboxes_per_owner(user1(), 0, None);
+
+    assert_eq!(boxes.len(), 1);
+    assert_eq!(boxes[0].box_status, JsonBoxStatus::NonClaimed);
+
+    // new boxes continue the numbering of the quest
+    testing_env!(context.attached_deposit(ONE_NEAR).build());
+
+    assert_eq!(contract.mint(0, user2(), BoxRarity::Rare), 1);
+    assert_eq!(contract.get_trusted_ft_contracts().len(), 3);
+
+    // boxes of the first release are enumerated along with the new ones
+    assert_eq!(contract.nft_total_supply(), U128(2));
+    assert_eq!(
+        contract
+            .nft_tokens(None, None)
+            .into_iter()
+            .map(|token| token.token_id)
+            .collect::<Vec<String>>(),
+        vec!["0:0", "0:1"]
+    );
+}
+
+#[test]
+fn test_migrate_keeps_migrated_state() {
+    let (mut contract, mut context) = setup_legacy();
+
+    contract.set_fee(100);
+
+    env::state_write(&contract);
+
+    let contract = Contract::migrate();
+
+    assert_eq!(contract.get_fees().fee_bps, 100);
+}
+
+#[test]
+fn test_delete_legacy_box_refunds_quest_owner() {
+    let (mut contract, mut context) = setup_legacy();
+
+    testing_env!(context.attached_deposit(1).build());
+
+    contract.delete_boxes(0, vec![0]);
+
+    assert!(contract.questboxes_per_owner(user1(), 0, None).is_empty());
+}
+
+#[test]
+fn test_migrate_legacy_quest_gets_default_tiers() {
+    let context = VMContextBuilder::new();
+
+    testing_env!(context.build());
+
+    let quest_id: QuestId = 0;
+    let quest_hash = env::sha256_array(&quest_id.to_be_bytes());
+
+    // fields of the quest as they were stored by the first release
+    let legacy_bytes = (
+        quest_id,
+        "first quest".to_string(),
+        owner(),
+        0 as PoolId,
+        LookupMap::<PoolId, LegacyPool>::new(StorageKey::Pools { quest_hash }),
+        LookupMap::<String, PoolId>::new(StorageKey::NftPoolByKey { quest_hash }),
+        LookupMap::<BoxRarity, HashSet<PoolId>>::new(StorageKey::PoolsByRarity { quest_hash }),
+        0 as BoxId,
+        LookupMap::<BoxId, QuestBox>::new(StorageKey::Boxes { quest_hash }),
+        LookupMap::<BoxRarity, Probability>::new(StorageKey::ProbabilityByRarity { quest_hash }),
+        UnorderedSet::<AccountId>::new(StorageKey::Users { quest_hash }),
+    )
+        .try_to_vec()
+        .unwrap();
+
+    let quest = Quest::from(LegacyQuest::try_from_slice(&legacy_bytes).unwrap());
+
+    let rarities: Vec<_> = quest.tiers.iter().map(|tier| tier.rarity.clone()).collect();
+
+    assert_eq!(quest.tiers, Quest::default_tiers());
+    assert_eq!(
+        rarities,
+        vec![BoxRarity::Rare, BoxRarity::Epic, BoxRarity::Legendary]
+    );
+}
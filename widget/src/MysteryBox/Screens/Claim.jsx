@@ -351,9 +351,93 @@ const nextActiveBox = () => {
   State.update({ active_box_index: state.active_box_index + 1 });
 };
 
+const maximumNameLength = 12;
+
+const shortenName = (name) =>
+  name.length > maximumNameLength
+    ? name.substring(0, maximumNameLength) + "..."
+    : name;
+
+const formatNearAmount = (amount) =>
+  Big(Big(amount).div(1e24).toFixed(2)).toNumber();
+
+const formatFtAmount = (contract_id, amount) => {
+  const metadata = Near.view(contract_id, "ft_metadata");
+
+  if (!metadata) return `${amount} ${shortenName(contract_id)}`;
+
+  const formatted = Big(
+    Big(amount).div(Big(10).pow(metadata.decimals)).toFixed(2)
+  ).toNumber();
+
+  return `${formatted} ${metadata.symbol}`;
+};
+
+const nftName = (contract_id) => {
+  const metadata = Near.view(contract_id, "nft_metadata");
+
+  return metadata ? metadata.name : contract_id;
+};
+
+// describes a pool of available_rewards
+const poolTitle = (reward) => {
+  if (reward.kind === "near") {
+    return `${formatNearAmount(reward.amount)} near token`;
+  } else if (reward.kind === "near_range") {
+    const min = formatNearAmount(reward.min_amount);
+    const max = formatNearAmount(reward.max_amount);
+
+    return `${min}-${max} near token`;
+  } else if (reward.kind === "non_fungible_token") {
+    return `${shortenName(nftName(reward.contract_id))} nft`;
+  } else if (reward.kind === "non_fungible_token_mint") {
+    const name = reward.metadata.title || nftName(reward.contract_id);
+
+    return `${shortenName(name)} nft`;
+  } else if (reward.kind === "fungible_token") {
+    return formatFtAmount(reward.contract_id, reward.amount);
+  } else if (reward.kind === "multi_token") {
+    return `${reward.amount} ${shortenName(reward.contract_id)}`;
+  } else if (reward.kind === "bundle") {
+    return reward.rewards.map(poolTitle).join(" + ");
+  }
+};
+
+const poolAmount = (reward) => {
+  // a range pool is limited by its budget rather than by a number of prizes
+  if (reward.kind === "near_range") {
+    return `${formatNearAmount(reward.available)} near left`;
+  }
+
+  const count = reward.available || reward.token_ids?.length;
+
+  const isPlural = count > 1;
+
+  const title = isPlural ? "prizes" : "prize";
+
+  return `${count} ${title}`;
+};
+
+// describes the reward of a claimed box
+const rewardTitle = (reward) => {
+  if (reward.kind === "near") {
+    return `${formatNearAmount(reward.amount)} near token`;
+  } else if (reward.kind === "non_fungible_token") {
+    return `${nftName(reward.contract_id)} nft`;
+  } else if (reward.kind === "fungible_token") {
+    return formatFtAmount(reward.contract_id, reward.amount);
+  } else if (reward.kind === "multi_token") {
+    return `${reward.amount} ${shortenName(reward.token_id)}`;
+  } else if (reward.kind === "bundle") {
+    return reward.rewards.map(rewardTitle).join(" + ");
+  } else if (reward.kind === "nothing") {
+    return "Better luck next time";
+  }
+};
+
 const NonClaimedBoxComponent = ({ box }) => {
   /** @todo: concat with the URL object */
-  const image = base_ipfs + box.ipfs;
+  const image = box.ipfs.startsWith("http") ? box.ipfs : base_ipfs + box.ipfs;
   const rarity = box.box_rarity;
 
   const onClick = () => {
@@ -364,35 +448,9 @@ const NonClaimedBoxComponent = ({ box }) => {
 
   const box_rewards = Array.isArray(box.rewards) ? box.rewards : [];
 
-  const amounts = box_rewards.map((reward) => {
-    const count = reward.available || reward.token_ids?.length;
-
-    const isPlural = count > 1;
+  const amounts = box_rewards.map(poolAmount);
 
-    const title = isPlural ? "prizes" : "prize";
-
-    return `${count} ${title}`;
-  });
-
-  const titles = box_rewards.map((reward) => {
-    if (reward.kind === "near") {
-      const amountInNear = Big(
-        Big(reward.amount).div(1e24).toFixed(2)
-      ).toNumber();
-
-      return `${amountInNear} near token`;
-    } else if (reward.kind === "non_fungible_token") {
-      const { name } = Near.view(reward.contract_id, "nft_metadata");
-
-      const maximumNameLength = 12;
-      const shortName =
-        name.length > maximumNameLength
-          ? name.substring(0, maximumNameLength) + "..."
-          : name;
-
-      return `${shortName} nft`;
-    }
-  });
+  const titles = box_rewards.map(poolTitle);
 
   return (
     <>
@@ -441,7 +499,7 @@ const LockIcon = ({}) => {
 
 const LockedBoxComponent = ({ box }) => {
   /** @todo: concat with the URL object */
-  const image = base_ipfs + box.ipfs;
+  const image = box.ipfs.startsWith("http") ? box.ipfs : base_ipfs + box.ipfs;
   const rarity = box.box_rarity;
 
   return (
@@ -461,28 +519,34 @@ const LockedBoxComponent = ({ box }) => {
   );
 };
 
-const OpenedBoxComponent = ({ box }) => {
+const ExpiredBoxComponent = ({ box }) => {
   /** @todo: concat with the URL object */
-  const image = base_ipfs + box.ipfs;
+  const image = box.ipfs.startsWith("http") ? box.ipfs : base_ipfs + box.ipfs;
   const rarity = box.box_rarity;
 
-  const reward = box.box_status.reward;
-
-  let text;
-
-  if (reward.kind === "near") {
-    const amountInNear = Big(
-      Big(reward.amount).div(1e24).toFixed(2)
-    ).toNumber();
+  return (
+    <>
+      <BoxTitleWrapper>
+        <BoxTitle>{rarity} box</BoxTitle>
+      </BoxTitleWrapper>
+      <BoxRewardWrapper />
+      <BoxImageWrapper>
+        <BoxImage src={image} />
+        <LockIcon />
+      </BoxImageWrapper>
+      <BoxBottomWrapper>
+        <BoxLockedTitle>Box expired</BoxLockedTitle>
+      </BoxBottomWrapper>
+    </>
+  );
+};
 
-    text = `${amountInNear} near token`;
-  } else if (reward.kind === "non_fungible_token") {
-    const { name } = Near.view(reward.contract_id, "nft_metadata");
+const OpenedBoxComponent = ({ box }) => {
+  /** @todo: concat with the URL object */
+  const image = box.ipfs.startsWith("http") ? box.ipfs : base_ipfs + box.ipfs;
+  const rarity = box.box_rarity;
 
-    text = `${name} nft`;
-  } else if (reward.kind === "nothing") {
-    text = "Better luck next time";
-  }
+  const text = rewardTitle(box.box_status.reward);
 
   return (
     <>
@@ -508,6 +572,9 @@ const BoxComponent = ({ box }) => {
   if (box.box_status.kind === "claimed")
     return <OpenedBoxComponent key={box.box_id} box={box} />;
 
+  if (box.box_status.kind === "expired")
+    return <ExpiredBoxComponent key={box.box_id} box={box} />;
+
   if (box.box_status.kind === "non_claimed" && box.rewards.length === 0)
     return <LockedBoxComponent key={box.box_id} box={box} />;
 